@@ -7,7 +7,7 @@ use std::{
 };
 
 /// The *runtime* representation of a lox class
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Class {
     inner: Rc<RefCell<ClassInner>>,
 }
@@ -16,6 +16,8 @@ pub struct Class {
 struct ClassInner {
     name: String,
     methods: Methods,
+    // the constructor of a class registered by the host, whose instances are native objects
+    ctor: Option<Function>,
 }
 
 type Methods = HashMap<String, LoxFunction>;
@@ -25,20 +27,83 @@ impl Class {
         let inner = ClassInner {
             name: name.as_ref().to_string(),
             methods,
+            ctor: None,
+        };
+        let inner = Rc::new(RefCell::new(inner));
+        Self { inner }
+    }
+
+    /// Creates a class whose instances are native objects made by the specified constructor.
+    pub fn native(ctor: NativeFunction) -> Self {
+        let inner = ClassInner {
+            name: ctor.name.clone(),
+            methods: Methods::default(),
+            ctor: Some(Function::Native(ctor)),
         };
         let inner = Rc::new(RefCell::new(inner));
         Self { inner }
     }
+
+    /// Counts the class's methods and the scopes they close over.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if !meter.first_visit(&self.inner, 0) {
+            return;
+        }
+        let Ok(inner) = self.inner.as_ref().try_borrow() else {
+            return;
+        };
+        for method in inner.methods.values() {
+            method.measure(meter);
+        }
+    }
+
+    /// A hash of the identity of the class, which is shared by its copies.
+    pub fn identity(&self) -> u64 {
+        Rc::as_ptr(&self.inner) as usize as u64
+    }
+
+    /// Looks up a method by name, without binding it to an instance.
+    pub fn find_method(&self, name: impl AsRef<str>) -> Option<LoxFunction> {
+        self.inner
+            .as_ref()
+            .borrow()
+            .methods
+            .get(name.as_ref())
+            .cloned()
+    }
+}
+
+// a class is only ever equal to itself. comparing the contents of two classes would compare the
+// closures of their methods, which may contain the classes themselves.
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
 }
 
 /// A Class is callable in the sense that the class itself is also a constructor
 impl Callable for Class {
     fn call(&self, int: &mut Interpreter, args: Vec<Value>) -> Result<Value, CallableError> {
+        let ctor = self.inner.as_ref().borrow().ctor.clone();
+        if let Some(ctor) = ctor {
+            return match ctor.call(int, args)? {
+                Value::Native(obj) => Ok(Value::Native(obj.with_class(self.clone()))),
+                other => Ok(other),
+            };
+        }
+        int.native_alloc(Instance::SIZE)?;
         Ok(Value::from(Instance::new(self.clone(), HashMap::default())))
     }
 
-    fn arity(&self) -> usize {
-        0
+    fn arity(&self) -> Arity {
+        match &self.inner.as_ref().borrow().ctor {
+            Some(ctor) => ctor.arity(),
+            None => Arity::exact(0),
+        }
+    }
+
+    fn name(&self) -> String {
+        self.inner.as_ref().borrow().name.clone()
     }
 }
 
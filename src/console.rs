@@ -0,0 +1,199 @@
+use crate::prelude::*;
+use std::io;
+
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    // prints the prompt, if any, without a newline before reading a line
+    int.define_fn("input", Arity::range(0, 1), |int, args| {
+        if let Some(prompt) = args.first() {
+            let prompt = stringify(int, prompt)?;
+            emit(int.stdout(), &prompt)?;
+        }
+        read_line(int)
+    });
+    int.define_fn("read_line", 0, |int, _| read_line(int));
+    int.define_fn("write", 1, |int, args| {
+        let s = stringify(int, &args[0])?;
+        emit(int.stdout(), &s)?;
+        Ok(Value::Nil)
+    });
+    int.define_fn("eprint", 1, |int, args| {
+        let s = stringify(int, &args[0])?;
+        emit(int.stderr(), &format!("{s}\n"))?;
+        Ok(Value::Nil)
+    });
+    int.define_fn("format", Arity::at_least(1), |int, args| {
+        // instances are formatted as the strings returned by their __str methods
+        let values = args[1..]
+            .iter()
+            .map(|arg| match arg {
+                Value::Instance(_) => stringify(int, arg).map(Value::String),
+                _ => Ok(arg.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // the output isn't reachable by scripts until it is returned, so it is checked against
+        // the memory limit as it grows and only charged once it is done.
+        let mut len = 0;
+        let out = format_charged(args[0].as_str()?, &values, |bytes| {
+            len += bytes;
+            int.reserve(len)
+        })?;
+        int.native_alloc(out.len())?;
+        Ok(Value::String(out))
+    });
+}
+
+fn stringify(int: &mut Interpreter, value: &Value) -> Result<String, CallableError> {
+    let line = int.call_line();
+    int.stringify(value, line)
+        .map_err(|err| CallableError::Call(err.into()))
+}
+
+// writes and flushes output that may not end in a newline.
+fn emit(out: &mut dyn io::Write, s: &str) -> Result<(), CallableError> {
+    out.write_all(s.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(|err| CallableError::Call(Box::new(interpreter::Error::Print(err))))
+}
+
+// reads a line from stdin without its line ending, returning nil at the end of the input.
+fn read_line(int: &mut Interpreter) -> Result<Value, CallableError> {
+    let mut line = String::new();
+    let n = int
+        .stdin()
+        .read_line(&mut line)
+        .map_err(|err| CallableError::Generic(err.into()))?;
+    if n == 0 {
+        return Ok(Value::Nil);
+    }
+    int.native_alloc(line.len())?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Value::String(line))
+}
+
+/// Formats args into a template. Each `{}` placeholder is replaced by the next arg, or by the
+/// arg at an index with `{0}`, and `{{` and `}}` are literal braces. A placeholder may have a
+/// spec after a colon of an optional alignment (`<`, `>`, or `^`), a minimum width, and a
+/// precision, as in `{:>8.2}`. Numbers are right aligned by default and other values left
+/// aligned. The precision is the number of decimal places of a number, or the maximum number of
+/// chars of any other value. Neither may be more than [`MAX_FORMAT_WIDTH`].
+pub fn format(template: &str, args: &[Value]) -> Result<String, ValueError> {
+    format_charged(template, args, |_| Ok(()))
+}
+
+/// The largest width or precision that a format placeholder may have.
+pub const MAX_FORMAT_WIDTH: usize = 1024;
+
+// formats args into a template, charging for the output of each placeholder before it is
+// written. the literal text is charged up front since it is no longer than the template.
+fn format_charged<E: From<ValueError>>(
+    template: &str,
+    args: &[Value],
+    mut charge: impl FnMut(usize) -> Result<(), E>,
+) -> Result<String, E> {
+    let err = |msg: &str| ValueError::Custom(format!("invalid format string: {msg}"));
+    charge(template.len())?;
+    let mut out = String::new();
+    let mut next_arg = 0;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '}' => return Err(err("unmatched '}'").into()),
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(|| err("unmatched '{'"))?;
+                let (index, spec) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+                let index = if index.is_empty() {
+                    next_arg += 1;
+                    next_arg - 1
+                } else {
+                    index
+                        .parse::<usize>()
+                        .map_err(|_| err(&format!("invalid index '{index}'")))?
+                };
+                let arg = args.get(index).ok_or_else(|| {
+                    err(&format!("no arg at index {index} of {} args", args.len()))
+                })?;
+                let spec = Spec::parse(spec).map_err(|msg| err(&msg))?;
+                spec.write(&mut out, arg, &mut charge)?;
+                chars = rest[end + 1..].chars();
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Default)]
+struct Spec {
+    align: Option<char>,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    // parses [align][width][.precision]
+    fn parse(src: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid spec '{src}'");
+        let (align, spec) = match src.chars().next() {
+            Some(c @ ('<' | '>' | '^')) => (Some(c), &src[1..]),
+            _ => (None, src),
+        };
+        let (width, precision) = match spec.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().map_err(|_| invalid())?)),
+            None => (spec, None),
+        };
+        let width = if width.is_empty() {
+            0
+        } else {
+            width.parse().map_err(|_| invalid())?
+        };
+        if width.max(precision.unwrap_or(0)) > MAX_FORMAT_WIDTH {
+            return Err(format!(
+                "width and precision of '{src}' must be at most {MAX_FORMAT_WIDTH}"
+            ));
+        }
+        Ok(Self {
+            align,
+            width,
+            precision,
+        })
+    }
+
+    fn write<E>(
+        &self,
+        out: &mut String,
+        arg: &Value,
+        charge: &mut impl FnMut(usize) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let (s, default_align) = match (arg, self.precision) {
+            (Value::Number(n), Some(p)) => (format!("{n:.p$}"), '>'),
+            (Value::Number(_), None) => (arg.to_lox(), '>'),
+            (_, Some(p)) => (arg.to_lox().chars().take(p).collect(), '<'),
+            (_, None) => (arg.to_lox(), '<'),
+        };
+        let pad = self.width.saturating_sub(s.chars().count());
+        charge(s.len() + pad)?;
+        let (left, right) = match self.align.unwrap_or(default_align) {
+            '<' => (0, pad),
+            '^' => (pad / 2, pad - pad / 2),
+            _ => (pad, 0),
+        };
+        out.extend(std::iter::repeat_n(' ', left));
+        out.push_str(&s);
+        out.extend(std::iter::repeat_n(' ', right));
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use crate::prelude::*;
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer},
+    DeserializeOwned, IntoDeserializer, Visitor,
+};
+
+/// Converts a lox value into a rust value. Instances and maps can be converted into structs or
+/// maps, and lists into sequences.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ValueError> {
+    T::deserialize(value)
+}
+
+impl de::Error for ValueError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            // integral numbers are visited as integers so that they can become integer types.
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                visitor.visit_i64(n as i64)
+            }
+            Value::Number(n) => visitor.visit_f64(n),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Nil => visitor.visit_unit(),
+            Value::List(list) => visitor.visit_seq(SeqDeserializer::new(list.values().into_iter())),
+            Value::Map(map) => {
+                visitor.visit_map(MapDeserializer::new(map.all_entries().into_iter()))
+            }
+            Value::Instance(instance) => {
+                visitor.visit_map(MapDeserializer::new(instance.fields().into_iter()))
+            }
+            other => Err(ValueError::Custom(format!(
+                "cannot convert {} to a rust value",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            Value::Nil | Value::Undefined => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Map(map) if map.len() == 1 => {
+                visitor.visit_enum(de::value::MapAccessDeserializer::new(MapDeserializer::new(
+                    map.entries().into_iter(),
+                )))
+            }
+            other => Err(other.type_error("enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
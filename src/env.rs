@@ -44,6 +44,11 @@ pub struct Env {
 }
 
 impl Env {
+    /// The approximate number of bytes allocated for a new scope.
+    pub const SCOPE_SIZE: usize = std::mem::size_of::<RefCell<Inner>>();
+    /// The approximate number of bytes allocated for a binding, excluding its name.
+    pub const RECORD_SIZE: usize = std::mem::size_of::<Record>();
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -97,7 +102,43 @@ impl Env {
     }
 
     pub fn get(&self, token: &Token) -> Result<Value> {
-        self.inner.as_ref().borrow().get(token, self.cursor)
+        self.lookup(token).ok_or_else(|| EnvError::not_found(token))
+    }
+
+    /// Whether both envs are the same scope with the same bindings visible.
+    pub fn ptr_eq(&self, other: &Env) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner) && self.cursor == other.cursor
+    }
+
+    /// Looks up a binding by name, returning None if it is not defined.
+    pub fn lookup(&self, name: impl AsRef<str>) -> Option<Value> {
+        self.inner
+            .as_ref()
+            .borrow()
+            .lookup(name.as_ref(), self.cursor)
+    }
+
+    /// The address of the scope, which identifies it while it is alive.
+    pub(crate) fn addr(&self) -> usize {
+        Rc::as_ptr(&self.inner) as *const () as usize
+    }
+
+    /// Counts the scope and its bindings, and queues its values and parent scope.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if !meter.first_visit(&self.inner, 0) {
+            return;
+        }
+        let Ok(inner) = self.inner.as_ref().try_borrow() else {
+            return;
+        };
+        meter.add(Self::SCOPE_SIZE);
+        for record in &inner.records {
+            meter.add(record.name.len() + Self::RECORD_SIZE);
+            meter.value(&record.val);
+        }
+        if let Some(parent) = &inner.parent {
+            meter.env(parent);
+        }
     }
 }
 
@@ -167,21 +208,18 @@ impl Inner {
         Err(EnvError::undefined_assign(name))
     }
 
-    fn get(&self, token: &Token, cursor: usize) -> Result<Value> {
+    fn lookup(&self, name: &str, cursor: usize) -> Option<Value> {
         let cursor = self.cursor(cursor);
         if let Some(found) = self
             .records
             .iter()
             .take(cursor)
             .rev()
-            .find(|r| &r.name == token.lexeme.as_ref())
+            .find(|r| r.name == name)
         {
-            return Ok(found.val.clone());
-        }
-        if let Some(parent) = &self.parent {
-            return parent.get(token);
+            return Some(found.val.clone());
         }
-        Err(EnvError::not_found(token))
+        self.parent.as_ref().and_then(|parent| parent.lookup(name))
     }
 }
 
@@ -12,6 +12,7 @@ pub enum Expr {
     Call(CallExpr),
     Get(GetExpr),
     Set(SetExpr),
+    This(ThisExpr),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -75,6 +76,11 @@ pub struct SetExpr {
     pub value: Box<Expr>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThisExpr {
+    pub keyword: Token,
+}
+
 impl Expr {
     pub fn binary(left: impl Into<Box<Expr>>, op: Token, right: impl Into<Box<Expr>>) -> Self {
         Self::Binary(BinaryExpr {
@@ -117,6 +123,7 @@ impl Expr {
             Expr::Call(e) => visitor.visit_call_expr(e),
             Expr::Get(e) => visitor.visit_get_expr(e),
             Expr::Set(e) => visitor.visit_set_expr(e),
+            Expr::This(e) => visitor.visit_this_expr(e),
         }
     }
 }
@@ -133,4 +140,5 @@ pub trait ExprVisitor {
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::Output;
     fn visit_get_expr(&mut self, expr: &GetExpr) -> Self::Output;
     fn visit_set_expr(&mut self, expr: &SetExpr) -> Self::Output;
+    fn visit_this_expr(&mut self, expr: &ThisExpr) -> Self::Output;
 }
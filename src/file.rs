@@ -0,0 +1,191 @@
+use crate::prelude::*;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+#[derive(thiserror::Error, Debug)]
+pub enum FileError {
+    #[error("{access} access denied for {}", path.display())]
+    Denied { path: PathBuf, access: Access },
+
+    #[error("{}: {err}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+}
+
+/// The kind of access that a script needs to a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// The dirs that scripts may access with the file natives. The default policy denies all
+/// access, so scripts can only touch files once a host has allowed the dirs that contain them.
+///
+/// Paths are checked after resolving symlinks and `..` components. A path that doesn't exist yet
+/// is checked against its nearest existing ancestor, and is rejected if it is a dangling symlink,
+/// since creating it would create the target of the link instead. The check is made before the
+/// file is opened, so it doesn't protect against other processes changing the dirs at the same
+/// time. Relative paths are resolved against the working dir.
+#[derive(Clone, Debug, Default)]
+pub struct FilePolicy {
+    dirs: Vec<(PathBuf, Access)>,
+}
+
+impl FilePolicy {
+    pub const fn new() -> Self {
+        Self { dirs: Vec::new() }
+    }
+
+    /// Allows scripts to read files within the dir and its subdirs.
+    pub fn allow_read(mut self, dir: impl AsRef<Path>) -> Self {
+        self.allow(dir.as_ref(), Access::Read);
+        self
+    }
+
+    /// Allows scripts to read, create, and write files within the dir and its subdirs.
+    pub fn allow_write(mut self, dir: impl AsRef<Path>) -> Self {
+        self.allow(dir.as_ref(), Access::Write);
+        self
+    }
+
+    fn allow(&mut self, dir: &Path, access: Access) {
+        let dir = resolve(dir).unwrap_or_else(|_| dir.to_path_buf());
+        self.dirs.push((dir, access));
+    }
+
+    /// Resolves a path that a script wants to access, returning an error if it is not within a
+    /// dir that allows the access.
+    pub fn check(&self, path: impl AsRef<Path>, access: Access) -> Result<PathBuf, FileError> {
+        let path = path.as_ref();
+        let denied = || FileError::Denied {
+            path: path.to_path_buf(),
+            access,
+        };
+        let resolved = resolve(path).map_err(|_| denied())?;
+        let allowed = self
+            .dirs
+            .iter()
+            .any(|(dir, allows)| *allows >= access && resolved.starts_with(dir));
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(denied())
+        }
+    }
+}
+
+// resolves a path to an absolute one without symlinks or relative components. the path may not
+// exist yet, in which case its longest existing ancestor is resolved instead. a missing path that
+// is a dangling symlink is an error.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let abs = std::path::absolute(path)?;
+    let mut existing = abs.as_path();
+    let mut missing = vec![];
+    loop {
+        match existing.canonicalize() {
+            Ok(real) => return Ok(missing.into_iter().rev().fold(real, |p, c| p.join(c))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if existing.symlink_metadata().is_ok() {
+                    return Err(io::Error::other("dangling symlink"));
+                }
+                // a missing path that ends in .. is rejected since it can't be resolved.
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        missing.push(name);
+                        existing = parent;
+                    }
+                    _ => return Err(err),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    int.define_fn("read_file", 1, |int, args| {
+        let src = read(int, &args[0])?;
+        Ok(Value::String(src))
+    });
+    int.define_fn("read_lines", 1, |int, args| {
+        let src = read(int, &args[0])?;
+        let lines = src.lines().map(Value::from).collect_vec();
+        int.native_alloc(lines.len() * std::mem::size_of::<Value>())?;
+        Ok(Value::List(lines.into()))
+    });
+    int.define_fn("write_file", 2, |int, args| {
+        write(int, &args[0], args[1].as_str()?, false)
+    });
+    int.define_fn("append_file", 2, |int, args| {
+        write(int, &args[0], args[1].as_str()?, true)
+    });
+    int.define_fn("exists", 1, |int, args| {
+        let path = int.file_policy().check(args[0].as_str()?, Access::Read)?;
+        Ok(path.exists().into())
+    });
+}
+
+// reads a file, reading no more than the remaining memory so that a large file raises the
+// memory limit's error instead of being read into memory first.
+fn read(int: &mut Interpreter, path: &Value) -> Result<String, CallableError> {
+    let path = int.file_policy().check(path.as_str()?, Access::Read)?;
+    let mut max_len = int.remaining_memory().unwrap_or(usize::MAX);
+    let mut src = String::new();
+    let too_large = fs::File::open(&path)
+        .and_then(|file| {
+            let len = file.metadata()?.len();
+            if len > max_len as u64 {
+                // credit back freed memory before deciding that it doesn't fit
+                max_len = int.measure_memory().unwrap_or(usize::MAX);
+            }
+            if len > max_len as u64 {
+                return Ok(true);
+            }
+            let limit = (max_len as u64).saturating_add(1);
+            file.take(limit).read_to_string(&mut src)?;
+            Ok(src.len() > max_len)
+        })
+        .map_err(|err| FileError::Io { path, err })?;
+    if too_large {
+        int.native_alloc(max_len.saturating_add(1))?;
+    }
+    int.native_alloc(src.len())?;
+    Ok(src)
+}
+
+fn write(
+    int: &mut Interpreter,
+    path: &Value,
+    contents: &str,
+    append: bool,
+) -> Result<Value, CallableError> {
+    let path = int.file_policy().check(path.as_str()?, Access::Write)?;
+    fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|err| FileError::Io { path, err })?;
+    Ok(Value::Nil)
+}
+
+impl From<FileError> for CallableError {
+    fn from(err: FileError) -> Self {
+        Self::Generic(err.into())
+    }
+}
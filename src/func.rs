@@ -11,12 +11,71 @@ pub enum CallableError {
 
     #[error("call: {0}")]
     Env(#[from] env::EnvError),
+
+    #[error(transparent)]
+    Value(#[from] ValueError),
+
+    #[error("expected {expected} args but got {actual}")]
+    Arity { expected: Arity, actual: usize },
 }
 
 /// This is the trait that all types which are callable must implement
+///
+/// Natives that call back into a script should use [`Interpreter::call_value`] rather than
+/// calling [`Callable::call`] directly, which skips the call depth limit, the step budget, and
+/// the frames that tracebacks are built from.
 pub trait Callable {
     fn call(&self, int: &mut Interpreter, args: Vec<Value>) -> Result<Value, CallableError>;
-    fn arity(&self) -> usize;
+    fn arity(&self) -> Arity;
+    fn name(&self) -> String;
+}
+
+/// The number of arguments that a callable accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    /// the maximum number of args, or None if the callable is variadic
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(n: usize) -> Self {
+        Self {
+            min: n,
+            max: Some(n),
+        }
+    }
+
+    pub fn range(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+
+    pub fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.min && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(n: usize) -> Self {
+        Self::exact(n)
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -25,31 +84,142 @@ pub enum Function {
     LoxFunction(LoxFunction),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct LoxFunction {
-    pub stmt: Box<FunctionStmt>,
+    pub stmt: Rc<FunctionStmt>,
     pub closure: env::Env,
+    /// the instance that a method is bound to, which is defined as `this` when it is called
+    pub this: Option<Instance>,
+    /// the path of the imported module that the function was declared in, or None if it was
+    /// declared in the script
+    pub module: Option<Rc<Path>>,
+}
+
+impl LoxFunction {
+    /// The approximate number of bytes allocated for a new closure.
+    pub const SIZE: usize = std::mem::size_of::<Self>() + std::mem::size_of::<FunctionStmt>();
+
+    pub fn new(stmt: Rc<FunctionStmt>, closure: env::Env) -> Self {
+        Self {
+            stmt,
+            closure,
+            this: None,
+            module: None,
+        }
+    }
+
+    /// The function as declared in the specified module.
+    pub(crate) fn in_module(self, module: Option<Rc<Path>>) -> Self {
+        Self { module, ..self }
+    }
+
+    /// Binds a method to an instance, which the method's body refers to as `this`.
+    pub fn bind(&self, instance: Instance) -> Self {
+        Self {
+            this: Some(instance),
+            ..self.clone()
+        }
+    }
+
+    /// Counts the closure, and queues the scope it closes over and the instance it is bound to.
+    /// Each closure is counted once, however many copies of it there are.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if meter.first_visit(&self.stmt, self.closure.addr()) {
+            meter.add(Self::SIZE);
+        }
+        meter.env(&self.closure);
+        if let Some(this) = &self.this {
+            meter.value(&Value::Instance(this.clone()));
+        }
+    }
+
+    // the scope that the body of the function runs in a child of: the closure, plus `this` for
+    // a bound method.
+    fn scope(&self) -> Result<env::Env, CallableError> {
+        let Some(this) = &self.this else {
+            return Ok(self.closure.clone());
+        };
+        let mut scope = self.closure.child();
+        scope.define("this", this.clone())?;
+        Ok(scope)
+    }
+}
+
+// functions have reference identity: a function is only equal to itself or a copy of itself,
+// not to another closure created from the same declaration. a method bound to the same instance
+// twice is the same function, so `a.f == a.f`.
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.stmt, &other.stmt)
+            && self.closure.ptr_eq(&other.closure)
+            && self.this == other.this
+    }
+}
+
+impl FunctionStmt {
+    /// Params with defaults may be omitted, and a rest param accepts any number of extra args.
+    pub fn arity(&self) -> Arity {
+        let min = self.params.iter().filter(|p| p.default.is_none()).count();
+        match self.rest {
+            Some(_) => Arity::at_least(min),
+            None => Arity::range(min, self.params.len()),
+        }
+    }
 }
 
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, CallableError>;
+
 #[derive(Clone)]
 pub struct NativeFunction {
     pub name: String,
-    pub arity: usize,
-    pub func: Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, CallableError>>,
+    pub arity: Arity,
+    pub func: Rc<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: impl AsRef<str>,
+        arity: impl Into<Arity>,
+        func: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, CallableError> + 'static,
+    ) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            arity: arity.into(),
+            func: Rc::new(func),
+        }
+    }
+
+    // the function bound to its name, for defining it in a built-in module.
+    pub(crate) fn binding(self) -> (String, Value) {
+        (self.name.clone(), Value::Function(Function::Native(self)))
+    }
 }
 
 impl Callable for Function {
     fn call(&self, int: &mut Interpreter, args: Vec<Value>) -> Result<Value, CallableError> {
         match self {
             Self::Native(NativeFunction { func, .. }) => func(int, args),
-            Self::LoxFunction(LoxFunction { stmt, closure }) => {
-                assert_eq!(stmt.params.len(), args.len());
-                let mut env = closure.child();
-                for (param, arg) in stmt.params.iter().zip(args.iter()) {
-                    env.define(param.lexeme.as_ref(), arg.clone())?;
+            Self::LoxFunction(func) => {
+                let stmt = &func.stmt;
+                let arity = stmt.arity();
+                if !arity.accepts(args.len()) {
+                    return Err(CallableError::Arity {
+                        expected: arity,
+                        actual: args.len(),
+                    });
                 }
-                let env = int.swap_env(env);
-                let res = int.execute_block(&stmt.body);
+                let params_size = stmt
+                    .params
+                    .iter()
+                    .map(|p| &p.name)
+                    .chain(&stmt.rest)
+                    .map(|p| p.name().len() + env::Env::RECORD_SIZE);
+                int.native_alloc(env::Env::SCOPE_SIZE + params_size.sum::<usize>())?;
+                let env = int.swap_env(func.scope()?.child());
+                int.enter_module(func.module.clone());
+                let res = int
+                    .bind_params(stmt, args)
+                    .and_then(|_| int.execute_block(&stmt.body));
                 int.restore_env(env);
                 match res {
                     Ok(()) => Ok(Value::Nil),
@@ -60,10 +230,30 @@ impl Callable for Function {
         }
     }
 
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         match self {
             Self::Native(NativeFunction { arity, .. }) => *arity,
-            Self::LoxFunction(func) => func.stmt.params.len(),
+            Self::LoxFunction(func) => func.stmt.arity(),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Native(NativeFunction { name, .. }) => name.clone(),
+            Self::LoxFunction(func) => func.stmt.name.name(),
+        }
+    }
+}
+
+impl Function {
+    /// A hash of the identity of the function, which is the same for functions that are equal.
+    pub fn identity(&self) -> u64 {
+        match self {
+            Self::Native(NativeFunction { func, .. }) => Rc::as_ptr(func) as *const () as u64,
+            Self::LoxFunction(func) => {
+                let stmt = Rc::as_ptr(&func.stmt) as usize;
+                (stmt ^ func.closure.addr().rotate_left(usize::BITS / 2)) as u64
+            }
         }
     }
 }
@@ -71,8 +261,8 @@ impl Callable for Function {
 impl PartialEq for Function {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Function::Native(n1), Function::Native(n2)) => n1.name == n2.name,
-            (Function::LoxFunction(n1), Function::LoxFunction(n2)) => n1.stmt == n2.stmt,
+            (Function::Native(n1), Function::Native(n2)) => Rc::ptr_eq(&n1.func, &n2.func),
+            (Function::LoxFunction(n1), Function::LoxFunction(n2)) => n1 == n2,
             _ => false,
         }
     }
@@ -5,13 +5,31 @@ use std::{collections::HashMap, fmt::Display};
 pub enum InstanceError {
     #[error("undefined property '{name}'")]
     UndefinedProperty { name: String },
+
+    #[error("invalid value for property '{name}': {err}")]
+    InvalidProperty {
+        name: String,
+        #[source]
+        err: ValueError,
+    },
+
+    #[error(transparent)]
+    Callable(#[from] CallableError),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Instance {
     inner: Rc<RefCell<Inner>>,
 }
 
+// instances have reference identity unless their class defines an equals method, which the
+// interpreter calls for the == and != operators.
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 struct Inner {
     class: Class,
@@ -24,23 +42,56 @@ pub struct Fields {
 }
 
 impl Instance {
+    /// Counts the instance and its fields, and queues their values and its class.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if !meter.first_visit(&self.inner, 0) {
+            return;
+        }
+        let Ok(inner) = self.inner.as_ref().try_borrow() else {
+            return;
+        };
+        meter.add(Self::SIZE);
+        for (name, value) in &inner.fields {
+            meter.add(name.len() + Self::FIELD_SIZE);
+            meter.value(value);
+        }
+        meter.value(&Value::Class(inner.class.clone()));
+    }
+
+    /// The approximate number of bytes allocated for a new instance, excluding its fields.
+    pub const SIZE: usize = std::mem::size_of::<RefCell<Inner>>();
+    /// The approximate number of bytes allocated for a field, excluding its name.
+    pub const FIELD_SIZE: usize = std::mem::size_of::<(String, Value)>();
+
     pub fn new(class: Class, fields: HashMap<String, Value>) -> Self {
         let inner = Inner { class, fields };
         let inner = Rc::new(RefCell::new(inner));
         Self { inner }
     }
 
+    /// Looks up a field, or a method of the instance's class bound to the instance if there is
+    /// no such field.
     pub fn get(&self, name: impl AsRef<str>) -> Result<Value, InstanceError> {
         let name = name.as_ref();
-        self.inner
-            .as_ref()
-            .borrow()
-            .fields
-            .get(name)
+        if let Some(val) = self.inner.as_ref().borrow().fields.get(name) {
+            return Ok(val.clone());
+        }
+        self.method(name)
+            .map(|method| Value::Function(Function::LoxFunction(method)))
             .ok_or_else(|| InstanceError::UndefinedProperty {
                 name: name.to_string(),
             })
-            .cloned()
+    }
+
+    /// Looks up a method of the instance's class, bound to the instance.
+    pub fn method(&self, name: impl AsRef<str>) -> Option<LoxFunction> {
+        let method = self.class().find_method(name)?;
+        Some(method.bind(self.clone()))
+    }
+
+    /// A hash of the identity of the instance, which is shared by its copies.
+    pub fn identity(&self) -> u64 {
+        Rc::as_ptr(&self.inner) as usize as u64
     }
 
     pub fn set(&self, name: impl AsRef<str>, value: Value) -> Result<Value, InstanceError> {
@@ -52,6 +103,15 @@ impl Instance {
             .insert(name.to_string(), value);
         Ok(Value::Nil)
     }
+
+    pub fn class(&self) -> Class {
+        self.inner.as_ref().borrow().class.clone()
+    }
+
+    /// Returns a copy of the fields currently set on the instance.
+    pub fn fields(&self) -> HashMap<String, Value> {
+        self.inner.as_ref().borrow().fields.clone()
+    }
 }
 
 impl Display for Instance {
@@ -1,9 +1,17 @@
 use crate::env::Env;
 use crate::prelude::*;
 use std::io::{self, stderr};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{cell::RefCell, collections::HashMap, io::stdout, ops::Neg, rc::Rc};
 
+/// The default maximum depth of nested function calls before a stack overflow error is raised.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// when less than this much native stack remains at a call, a new segment of the specified size
+// is allocated for it. a single lox call takes roughly 16KiB of stack in a debug build.
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("expected numbers for op: {op}")]
@@ -27,28 +35,56 @@ pub enum Error {
     #[error("line {}: can only call functions and classes", token.line)]
     NotAFunction { token: Token },
 
+    #[error("undefined global '{name}'")]
+    UndefinedGlobal { name: String },
+
+    #[error("global '{name}' is not a function or class")]
+    NotCallable { name: String },
+
     #[error("line {}: only instances have properties", token.line)]
     OnlyInstancesHaveProperties { token: Token },
 
-    #[error("line {}: expected {expected} args but got {actual}", token.line)]
+    #[error("line {line}: expected {expected} args but got {actual}")]
     FunctionArity {
-        token: Token,
-        expected: usize,
+        line: usize,
+        expected: Arity,
         actual: usize,
     },
 
     #[error(transparent)]
     CallableError(#[from] CallableError),
 
+    #[error("{err}")]
+    NativeCall {
+        line: usize,
+        #[source]
+        err: CallableError,
+    },
+
     #[error("could not print: {0}")]
     Print(#[source] io::Error),
 
     #[error("not an actual error! used to unwind the call stack.")]
     Return(Value),
 
+    #[error("exited with code {0}")]
+    Exit(u8),
+
+    #[error(transparent)]
+    Limit(#[from] LimitError),
+
+    #[error("line {line}: Stack overflow")]
+    StackOverflow { line: usize },
+
+    #[error("line {line}: uncaught exception: {}", value.to_lox())]
+    Throw { value: Value, line: usize },
+
     #[error("can't return from top-level code.")]
     TopLevelReturn,
 
+    #[error("line {line}: can't use 'this' outside of a method")]
+    ThisOutsideMethod { line: usize },
+
     #[error("line {}: {err}", token.line)]
     InstanceError {
         token: Token,
@@ -58,53 +94,184 @@ pub enum Error {
 
     #[error("class method stmt is not a function")]
     ClassStmtNotFunction,
+
+    #[error("line {line}: could not import '{path}': {msg}")]
+    Import {
+        line: usize,
+        path: String,
+        msg: String,
+    },
+
+    #[error(
+        "line {line}: circular import: {}",
+        chain.iter().map(|path| path.display()).join(" -> ")
+    )]
+    CircularImport { line: usize, chain: Vec<PathBuf> },
+
+    #[error("{trace}{err}")]
+    Traced {
+        trace: Traceback,
+        #[source]
+        err: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The line the error occurred on, if the error carries that information.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::NumbersRequired { op }
+            | Self::TwoNumbersOrStringsRequired { op }
+            | Self::InvalidBinaryOp { op } => Some(op.line),
+            Self::UndfinedVar { token }
+            | Self::NotAFunction { token }
+            | Self::OnlyInstancesHaveProperties { token }
+            | Self::InstanceError { token, .. }
+            | Self::Env(env::EnvError::NotFound { token }) => Some(token.line),
+            Self::DivideByZero { line }
+            | Self::FunctionArity { line, .. }
+            | Self::StackOverflow { line }
+            | Self::Throw { line, .. }
+            | Self::ThisOutsideMethod { line }
+            | Self::NativeCall { line, .. }
+            | Self::Import { line, .. }
+            | Self::CircularImport { line, .. } => Some(*line),
+            Self::CallableError(CallableError::Call(err)) => err.line(),
+            Self::Traced { err, .. } => err.line(),
+            _ => None,
+        }
+    }
+
+    // strips away the errors that wrap a runtime error as it unwinds through function calls.
+    fn into_root(self) -> Self {
+        match self {
+            Self::CallableError(CallableError::Call(err)) => err.into_root(),
+            err => err,
+        }
+    }
 }
 
 pub struct Interpreter {
     env: Env,
+    globals: Env,
+    stdin: Box<dyn io::BufRead>,
     stdout: Box<dyn io::Write>,
     stderr: Box<dyn io::Write>,
     fn_depth: usize,
+    max_depth: usize,
+    budget: Budget,
+    frames: Vec<Frame>,
+    // the call stack as it was when the most recent runtime error was raised
+    trace: Option<Traceback>,
+    // the class of the values that runtime errors are converted into when caught
+    error_class: Class,
+    rng: Rng,
+    clock: Box<dyn Clock>,
+    // the dirs that scripts may access, or None if the host hasn't set a policy. the file
+    // natives are denied all access without one, but imports are not restricted.
+    file_policy: Option<FilePolicy>,
+    // the environment variables that scripts can read, which are none unless the host passes
+    // them in
+    env_vars: HashMap<String, String>,
+    // the globals defined by the prelude and the host, which every module starts out with
+    builtins: Vec<(String, Value)>,
+    // the path of the script being run, if it was read by the module loader
+    script: Option<PathBuf>,
+    loader: Box<dyn ModuleLoader>,
+    // the dirs that imports are resolved against when they are not found relative to the
+    // importing script
+    search_path: Vec<PathBuf>,
+    // the globals of each module that has finished executing, keyed by its resolved path
+    modules: HashMap<PathBuf, Env>,
+    // the resolved paths of the modules that are currently executing, innermost last
+    importing: Vec<PathBuf>,
+    // the resolved path of the imported module whose code is running, or None for the script
+    module: Option<Rc<Path>>,
+    // the envs that were swapped out for the env of a call or module, innermost last, which
+    // are still live while it runs
+    saved_envs: Vec<Env>,
 }
 
 impl Default for Interpreter {
     fn default() -> Self {
         let mut env = Env::default();
-        env.define(
-            "clock",
-            Value::Function(Function::Native(NativeFunction {
-                name: "clock".to_string(),
-                arity: 0,
-                func: Rc::new(|interpreter: &mut Interpreter, args: Vec<Value>| {
-                    let now = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .map_err(|err| CallableError::Generic(err.into()))?;
-                    Ok(Value::Number(now.as_secs_f64()))
-                }),
-            })),
-        )
-        .unwrap();
-        Self {
+        let error_class = Class::new("Error", HashMap::default());
+        env.define("Error", error_class.clone()).unwrap();
+        let mut interpreter = Self {
+            globals: env.clone(),
             env,
+            stdin: Box::new(io::BufReader::new(stdin())),
             stdout: Box::new(stdout()),
             stderr: Box::new(stderr()),
             fn_depth: 0,
-        }
+            max_depth: DEFAULT_MAX_DEPTH,
+            budget: Budget::default(),
+            frames: vec![],
+            trace: None,
+            builtins: vec![("Error".to_string(), error_class.clone().into())],
+            error_class,
+            rng: Rng::default(),
+            clock: Box::new(SystemClock::default()),
+            file_policy: None,
+            env_vars: HashMap::default(),
+            script: None,
+            loader: Box::new(FsLoader),
+            search_path: vec![],
+            modules: HashMap::default(),
+            importing: vec![],
+            module: None,
+            saved_envs: vec![],
+        };
+        interpreter.define_fn("clock", 0, |int, _| {
+            Ok(Value::Number(int.clock().now().as_secs_f64()))
+        });
+        json::define_natives(&mut interpreter);
+        math::define_module(&mut interpreter);
+        random::define_module(&mut interpreter);
+        file::define_natives(&mut interpreter);
+        console::define_natives(&mut interpreter);
+        process::define_natives(&mut interpreter);
+        time::define_module(&mut interpreter);
+        reflect::define_natives(&mut interpreter);
+        map::define_natives(&mut interpreter);
+        interpreter
     }
 }
 
 impl Interpreter {
     pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<(), Error> {
+        self.trace = None;
+        self.budget.reset();
         for stmt in stmts {
-            self.execute(stmt)?;
+            self.execute(stmt).map_err(|err| self.traced(err))?;
         }
         Ok(())
     }
 
+    /// Evaluates a top-level expression, attaching a traceback to any uncaught error.
+    pub fn interpret_expr(&mut self, expr: &Expr) -> Result<Value, Error> {
+        self.trace = None;
+        self.budget.reset();
+        self.evaluate(expr).map_err(|err| self.traced(err))
+    }
+
+    /// Evaluates a top-level expression and prints its value the same as a `print` statement on
+    /// the specified line, which is how the REPL shows the result of an expression.
+    pub fn interpret_and_print(&mut self, expr: &Expr, line: Option<usize>) -> Result<(), Error> {
+        let value = self.interpret_expr(expr)?;
+        self.print_value(&value, line)
+            .map_err(|err| self.traced(err))
+    }
+
     pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, Error> {
         expr.accept(self)
     }
 
+    pub fn with_stdin(mut self, r: Box<dyn io::BufRead>) -> Self {
+        self.stdin = r;
+        self
+    }
+
     pub fn with_stdout(mut self, w: Box<dyn io::Write>) -> Self {
         self.stdout = w;
         self
@@ -115,7 +282,552 @@ impl Interpreter {
         self
     }
 
+    /// Sets the maximum depth of nested function calls.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets the maximum number of steps (loop iterations and function calls) that a single run
+    /// may take.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.budget.limits_mut().max_steps.replace(steps);
+        self
+    }
+
+    /// Sets the maximum wall-clock time that a single run may take.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.budget.limits_mut().timeout.replace(timeout);
+        self
+    }
+
+    /// Sets the maximum number of bytes of live memory that scripts may use. Memory that is
+    /// already in use, such as the prelude, isn't counted, and memory that scripts no longer
+    /// refer to is freed, so the limit applies across runs rather than to each one.
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        let baseline = self.live_memory();
+        self.budget.set_max_memory(bytes, baseline);
+        self
+    }
+
+    /// Sets the list of command line args that scripts see as the global `args`.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        let args = args.into_iter().map(Value::String).collect_vec();
+        self.define_builtin("args", Value::List(args.into()));
+        self
+    }
+
+    /// Seeds the generator used by the `random` module so that runs are reproducible. Without a
+    /// seed the generator is seeded from the system clock.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// The random number generator used by the `random` module.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Sets the source of the current time for `clock` and the `time` module.
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The source of the current time for scripts.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Sets the dirs that scripts may access with the file natives, which are denied all access
+    /// by default. Once a policy is set, modules imported from the filesystem must also be
+    /// within a dir that it allows reading.
+    pub fn with_file_policy(mut self, policy: FilePolicy) -> Self {
+        self.file_policy = Some(policy);
+        self
+    }
+
+    pub(crate) fn file_policy(&self) -> &FilePolicy {
+        static DENY_ALL: FilePolicy = FilePolicy::new();
+        self.file_policy.as_ref().unwrap_or(&DENY_ALL)
+    }
+
+    /// Sets the environment variables that scripts can read with `getenv`. Scripts can't see
+    /// the host's environment unless it is passed in here.
+    pub fn with_env_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.env_vars = vars;
+        self
+    }
+
+    pub(crate) fn env_var(&self, name: &str) -> Option<&str> {
+        self.env_vars.get(name).map(String::as_str)
+    }
+
+    /// Looks up a value in the global scope.
+    pub fn get_global(&self, name: impl AsRef<str>) -> Option<Value> {
+        self.globals.lookup(name)
+    }
+
+    /// Calls the global function with the specified name from the host, attaching a traceback
+    /// to any uncaught error in the same way as a top-level run.
+    pub fn call_global(&mut self, name: impl AsRef<str>, args: Vec<Value>) -> Result<Value, Error> {
+        self.trace = None;
+        self.budget.reset();
+        let name = name.as_ref();
+        let callee = self
+            .get_global(name)
+            .ok_or_else(|| Error::UndefinedGlobal {
+                name: name.to_string(),
+            })?;
+        let Some(callable) = callee.as_callable() else {
+            return Err(Error::NotCallable {
+                name: name.to_string(),
+            });
+        };
+        self.call(callable, args, None)
+            .map_err(|err| self.traced(err))
+    }
+
+    /// Calls a function or class value from a native, such as a callback that a script passed to
+    /// it. The call is made as if from the line that the native was called from, so it counts
+    /// towards the call depth and the step budget and shows up in tracebacks.
+    pub fn call_value(&mut self, callee: &Value, args: Vec<Value>) -> Result<Value, CallableError> {
+        let callable = callee
+            .as_callable()
+            .ok_or_else(|| callee.type_error("function"))?;
+        let line = self.call_line();
+        self.call(callable, args, line)
+            .map_err(|err| CallableError::Call(Box::new(err)))
+    }
+
+    // calls a callable with its args, maintaining the call stack. the line of the call site is
+    // None when the call was made by the host.
+    fn call(
+        &mut self,
+        callable: &dyn Callable,
+        args: Vec<Value>,
+        line: Option<usize>,
+    ) -> Result<Value, Error> {
+        let arity = callable.arity();
+        if !arity.accepts(args.len()) {
+            let actual = args.len();
+            return Err(match line {
+                Some(line) => Error::FunctionArity {
+                    line,
+                    expected: arity,
+                    actual,
+                },
+                None => CallableError::Arity {
+                    expected: arity,
+                    actual,
+                }
+                .into(),
+            });
+        }
+        self.budget.step()?;
+        if self.fn_depth >= self.max_depth {
+            return Err(Error::StackOverflow {
+                line: line.unwrap_or_default(),
+            });
+        }
+        self.fn_depth += 1;
+        // a lox function switches to the module it was declared in while it runs
+        let module = self.module.clone();
+        self.frames.push(Frame {
+            name: callable.name(),
+            line,
+            module: module.clone(),
+        });
+        // each lox call recurses through several visitor methods, so the native stack is grown
+        // on demand rather than limiting scripts to the stack of the host's thread.
+        let fn_res = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            callable.call(self, args)
+        });
+        if let (Err(err), None) = (&fn_res, &self.trace) {
+            let line = match err {
+                CallableError::Call(err) => err.line(),
+                _ => None,
+            };
+            let trace = Traceback::new(self.frames.clone(), line, self.module.clone());
+            self.trace.replace(trace);
+        }
+        self.frames.pop();
+        self.module = module;
+        self.fn_depth -= 1;
+        match (fn_res, line) {
+            // errors raised by natives don't know where they were called from, so they are
+            // given the line of the call site. errors from lox code already carry their line.
+            (Err(err), Some(line)) if !matches!(err, CallableError::Call(_)) => {
+                Err(Error::NativeCall { line, err })
+            }
+            (res, _) => Ok(res?),
+        }
+    }
+
+    // switches to the module of a lox function that is being called, until the call returns.
+    pub(crate) fn enter_module(&mut self, module: Option<Rc<Path>>) {
+        self.module = module;
+    }
+
+    /// The line that the function currently being called was called from, or None if it was
+    /// called by the host. Natives use this to report errors from lox code that they call back
+    /// into.
+    pub fn call_line(&self) -> Option<usize> {
+        self.frames.last()?.line
+    }
+
+    /// Defines a native function in the global scope, replacing any existing global binding with
+    /// the same name.
+    pub fn define_fn(
+        &mut self,
+        name: impl AsRef<str>,
+        arity: impl Into<Arity>,
+        func: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, CallableError> + 'static,
+    ) {
+        let native = NativeFunction::new(name.as_ref(), arity, func);
+        self.define_builtin(name, Value::Function(Function::Native(native)));
+    }
+
+    /// Defines a built-in module in the global scope, such as `math`, whose values scripts
+    /// access as properties of the module.
+    pub fn define_module(
+        &mut self,
+        name: impl AsRef<str>,
+        bindings: impl IntoIterator<Item = (String, Value)>,
+    ) {
+        let module = Module::builtin(name.as_ref(), bindings);
+        self.define_builtin(name, Value::Module(module));
+    }
+
+    // defines a global that is also visible to every imported module.
+    pub(crate) fn define_builtin(&mut self, name: impl AsRef<str>, value: Value) {
+        let name = name.as_ref().to_string();
+        self.globals
+            .define(&name, value.clone())
+            .expect("globals allow redefinition");
+        self.builtins.retain(|(n, _)| *n != name);
+        self.builtins.push((name, value));
+    }
+
+    /// Sets the loader that scripts and the modules they import are read with.
+    pub fn with_loader(mut self, loader: Box<dyn ModuleLoader>) -> Self {
+        self.loader = loader;
+        self
+    }
+
+    /// Sets the dirs that imports are resolved against, in order, when a module is not found
+    /// relative to the importing script.
+    pub fn with_search_path(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.search_path = dirs;
+        self
+    }
+
+    /// Reads a script with the module loader and sets it as the script being run. Imports in
+    /// the script are resolved relative to the dir that contains it, or to the working dir if no
+    /// script has been loaded.
+    pub fn load_script(&mut self, path: impl AsRef<Path>) -> io::Result<String> {
+        let resolved = self.loader.resolve(path.as_ref())?;
+        let src = self.loader.load(&resolved)?;
+        self.script.replace(resolved);
+        Ok(src)
+    }
+
+    // resolves an import path relative to the importing script, falling back to each dir of the
+    // search path in turn.
+    fn resolve_module(&self, path: &str) -> io::Result<PathBuf> {
+        let importer = self.importing.last().or(self.script.as_ref());
+        let base = importer.and_then(|p| p.parent()).unwrap_or(Path::new(""));
+        let dirs = std::iter::once(base).chain(self.search_path.iter().map(PathBuf::as_path));
+        let mut not_found = None;
+        for dir in dirs {
+            match self.loader.resolve(&dir.join(path)) {
+                Ok(resolved) => return Ok(resolved),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    not_found.get_or_insert(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(not_found.expect("at least one dir is searched"))
+    }
+
+    // executes the module at the specified path and returns its resolved path and globals. each
+    // module is only executed once, on its first import.
+    fn load_module(&mut self, path: &str, line: usize) -> Result<(PathBuf, Env), Error> {
+        let import_err = |msg: String| Error::Import {
+            line,
+            path: path.to_string(),
+            msg,
+        };
+        let resolved = self
+            .resolve_module(path)
+            .map_err(|err| import_err(err.to_string()))?;
+        if let (Some(policy), true) = (&self.file_policy, self.loader.on_filesystem()) {
+            policy
+                .check(&resolved, Access::Read)
+                .map_err(|err| import_err(err.to_string()))?;
+        }
+        if let Some(globals) = self.modules.get(&resolved) {
+            return Ok((resolved, globals.clone()));
+        }
+        let running = self.script.iter().chain(&self.importing);
+        if let Some(start) = running.clone().position(|p| *p == resolved) {
+            let mut chain = running.skip(start).cloned().collect_vec();
+            chain.push(resolved);
+            return Err(Error::CircularImport { line, chain });
+        }
+
+        let src = self
+            .loader
+            .load(&resolved)
+            .map_err(|err| import_err(err.to_string()))?;
+        let tokens = Scanner::new(src)
+            .scan_tokens()
+            .map_err(|err| import_err(err.to_string()))?;
+        let stmts = Parser::new(tokens)
+            .parse()
+            .map_err(|err| import_err(err.to_string()))?;
+
+        let mut globals = Env::default();
+        for (name, value) in &self.builtins {
+            globals.define(name, value.clone())?;
+        }
+        let prev = self.swap_env(globals.clone());
+        // the module's top level is not inside the function that imported it, if any.
+        let fn_depth = std::mem::take(&mut self.fn_depth);
+        self.importing.push(resolved.clone());
+        // the import shows up in tracebacks as a call of the module's top level
+        let module: Rc<Path> = resolved.as_path().into();
+        let importer = self.module.replace(module.clone());
+        self.frames.push(Frame {
+            name: "<module>".to_string(),
+            line: Some(line),
+            module: importer.clone(),
+        });
+        let res = stmts.iter().try_for_each(|stmt| self.execute(stmt));
+        match (&res, &self.trace) {
+            // errors from imports already say which module they are about
+            (Err(Error::Import { .. } | Error::CircularImport { .. }), _) | (_, Some(_)) => {}
+            (Err(err), None) => {
+                let trace = Traceback::new(self.frames.clone(), err.line(), Some(module));
+                self.trace.replace(trace);
+            }
+            (Ok(()), None) => {}
+        }
+        self.frames.pop();
+        self.module = importer;
+        self.importing.pop();
+        self.fn_depth = fn_depth;
+        self.restore_env(prev);
+        res?;
+        self.modules.insert(resolved.clone(), globals.clone());
+        Ok((resolved, globals))
+    }
+
+    /// Compares two values with `==`. If either is an instance whose class defines an `__eq` or
+    /// `equals` method, the method of the left operand, or else the right, decides. Other
+    /// instances, functions, classes and maps are only equal to themselves. Lists are equal if
+    /// their elements are, compared the same way, and all other values are compared by value.
+    pub fn values_equal(
+        &mut self,
+        a: &Value,
+        b: &Value,
+        line: Option<usize>,
+    ) -> Result<bool, Error> {
+        self.values_equal_within(a, b, line, &mut vec![])
+    }
+
+    // compares values as values_equal does, given the pairs of lists that are being compared
+    // further up. those are taken to be equal, so that lists that contain themselves can be
+    // compared.
+    fn values_equal_within(
+        &mut self,
+        a: &Value,
+        b: &Value,
+        line: Option<usize>,
+        comparing: &mut Vec<(List, List)>,
+    ) -> Result<bool, Error> {
+        for (this, other) in [(a, b), (b, a)] {
+            for name in ["__eq", "equals"] {
+                if let Some(res) = self.call_special(this, name, vec![other.clone()], line)? {
+                    return Ok(res.truthy());
+                }
+            }
+        }
+        let (Value::List(l1), Value::List(l2)) = (a, b) else {
+            return Ok(a == b);
+        };
+        let in_progress = |(x, y): &(List, List)| x.ptr_eq(l1) && y.ptr_eq(l2);
+        if l1.ptr_eq(l2) || comparing.iter().any(in_progress) {
+            return Ok(true);
+        }
+        if l1.len() != l2.len() {
+            return Ok(false);
+        }
+        comparing.push((l1.clone(), l2.clone()));
+        let mut res = Ok(true);
+        for idx in 0..l1.len() {
+            // an equals method may have shortened the lists
+            let (Some(x), Some(y)) = (l1.get(idx), l2.get(idx)) else {
+                res = Ok(false);
+                break;
+            };
+            res = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+                self.values_equal_within(&x, &y, line, comparing)
+            });
+            if !matches!(res, Ok(true)) {
+                break;
+            }
+        }
+        comparing.pop();
+        res
+    }
+
+    /// Converts a value into the string that `print` writes. An instance whose class defines a
+    /// `__str` method is converted by calling it, and the method must return a string. The line is
+    /// that of the code that is converting the value, for reporting errors from `__str`.
+    pub fn stringify(&mut self, value: &Value, line: Option<usize>) -> Result<String, Error> {
+        match self.call_special(value, "__str", vec![], line)? {
+            Some(Value::String(s)) => Ok(s),
+            Some(other) => {
+                let msg = format!("__str must return a string but got {}", other.type_name());
+                Err(native_error(ValueError::Custom(msg), line))
+            }
+            None => Ok(value.to_lox()),
+        }
+    }
+
+    // writes a value to stdout as print does.
+    fn print_value(&mut self, value: &Value, line: Option<usize>) -> Result<(), Error> {
+        let s = self.stringify(value, line)?;
+        writeln!(self.stdout(), "{s}").map_err(Error::Print)
+    }
+
+    // calls a special method such as __add, returning None if the value is not an instance of a
+    // class that defines the method.
+    fn call_special(
+        &mut self,
+        value: &Value,
+        name: &str,
+        args: Vec<Value>,
+        line: Option<usize>,
+    ) -> Result<Option<Value>, Error> {
+        let Value::Instance(instance) = value else {
+            return Ok(None);
+        };
+        let Some(method) = instance.method(name) else {
+            return Ok(None);
+        };
+        self.call(&Function::LoxFunction(method), args, line)
+            .map(Some)
+    }
+
+    /// Hashes a value that is used as a map key, such that equal values have equal hashes. An
+    /// instance is hashed by the number returned by its class's `hash` method, or else by its
+    /// identity, so a class that defines `equals` should define `hash` too. Functions and classes
+    /// are hashed by their identity. The line is that of the code that is hashing the value, for
+    /// reporting errors.
+    pub fn hash_value(&mut self, value: &Value, line: Option<usize>) -> Result<u64, Error> {
+        let unhashable = |value: &Value| {
+            let msg = format!("{} can't be used as a map key", value.type_name());
+            native_error(ValueError::Custom(msg), line)
+        };
+        if let Some(hash) = map::hash_primitive(value) {
+            return Ok(hash);
+        }
+        match value {
+            Value::Instance(instance) => {
+                let Some(hash) = instance.method("hash") else {
+                    return Ok(instance.identity());
+                };
+                let hash = self.call(&Function::LoxFunction(hash), vec![], line)?;
+                let n = hash.as_number().map_err(|_| {
+                    let msg = format!("hash must return a number but got {}", hash.type_name());
+                    native_error(ValueError::Custom(msg), line)
+                })?;
+                Ok(map::hash_number(n))
+            }
+            Value::Function(func) => Ok(func.identity()),
+            Value::Class(class) => Ok(class.identity()),
+            other => Err(unhashable(other)),
+        }
+    }
+
+    /// Charges an allocation of the specified size against the memory limit. Memory that has
+    /// been freed is only credited back when the charges would exceed the limit, at which point
+    /// the memory that is still reachable is measured.
+    pub fn alloc(&mut self, bytes: usize) -> Result<(), Error> {
+        if !self.budget.alloc(bytes) {
+            let live = self.live_memory();
+            self.budget.measured(live, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// The time left before the current run times out, or None if it has no timeout.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.budget.remaining_time()
+    }
+
+    /// The number of bytes that can certainly still be allocated, or None if there is no memory
+    /// limit. More may be available once freed memory is credited back by
+    /// [`Interpreter::measure_memory`].
+    pub fn remaining_memory(&self) -> Option<usize> {
+        self.budget.remaining_memory()
+    }
+
+    /// Measures the memory that is still reachable, crediting back any that has been freed, and
+    /// returns the number of bytes that can still be allocated, or None if there is no memory
+    /// limit.
+    pub fn measure_memory(&mut self) -> Option<usize> {
+        self.budget.remaining_memory()?;
+        let live = self.live_memory();
+        // measuring without allocating can't exceed the limit unless it already was
+        let _ = self.budget.measured(live, 0);
+        self.budget.remaining_memory()
+    }
+
+    // measures the memory reachable from the envs and modules. values that are only held by the
+    // rust stack while an expression is being evaluated are not counted.
+    fn live_memory(&self) -> usize {
+        let mut meter = Meter::default();
+        for env in [&self.env, &self.globals]
+            .into_iter()
+            .chain(&self.saved_envs)
+        {
+            meter.env(env);
+        }
+        for env in self.modules.values() {
+            meter.env(env);
+        }
+        for (_, value) in &self.builtins {
+            meter.value(value);
+        }
+        meter.finish()
+    }
+
+    /// Charges an allocation like [`Interpreter::alloc`], returning the error that natives
+    /// return.
+    pub fn native_alloc(&mut self, bytes: usize) -> Result<(), CallableError> {
+        self.alloc(bytes)
+            .map_err(|err| CallableError::Call(Box::new(err)))
+    }
+
+    /// Checks that the specified number of bytes could be allocated without exceeding the memory
+    /// limit, without charging for them. Natives that build up a large value call this as it
+    /// grows, since memory held only by the native isn't found when live memory is measured, and
+    /// then charge for the finished value with [`Interpreter::native_alloc`].
+    pub fn reserve(&mut self, bytes: usize) -> Result<(), CallableError> {
+        let fits = |remaining: Option<usize>| remaining.is_none_or(|rem| bytes <= rem);
+        if fits(self.remaining_memory()) || fits(self.measure_memory()) {
+            return Ok(());
+        }
+        self.native_alloc(bytes)
+    }
+
     pub fn execute_block(&mut self, stmts: &[Stmt]) -> Result<(), Error> {
+        self.alloc(Env::SCOPE_SIZE)?;
         self.env.push();
         let res = (|| {
             for stmt in stmts {
@@ -127,12 +839,38 @@ impl Interpreter {
         res
     }
 
+    /// Binds the args of a call to the params of a function in the current env. Params that were
+    /// not supplied are assigned their defaults, evaluated in the scope of the function so that
+    /// they can refer to earlier params. Any extra args are collected into the rest param.
+    pub fn bind_params(&mut self, func: &FunctionStmt, args: Vec<Value>) -> Result<(), Error> {
+        let mut args = args.into_iter();
+        for param in &func.params {
+            let value = match (args.next(), &param.default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => self.evaluate(default)?,
+                // the arity check normally prevents this, but if not the param is left
+                // undefined, the same as a var without an initializer.
+                (None, None) => Value::Undefined,
+            };
+            self.env.define(&param.name, value)?;
+        }
+        if let Some(rest) = &func.rest {
+            let rest_args = args.collect_vec();
+            self.alloc(rest_args.len() * std::mem::size_of::<Value>())?;
+            self.env.define(rest, List::new(rest_args))?;
+        }
+        Ok(())
+    }
+
     pub fn swap_env(&mut self, env: Env) -> Env {
-        std::mem::replace(&mut self.env, env)
+        let prev = std::mem::replace(&mut self.env, env);
+        self.saved_envs.push(prev.clone());
+        prev
     }
 
-    // replaces the env with the supplied one
+    // replaces the env with the supplied one, which was returned by the matching swap_env
     pub fn restore_env(&mut self, env: Env) {
+        self.saved_envs.pop();
         self.env = env;
     }
 
@@ -140,15 +878,80 @@ impl Interpreter {
         stmt.accept(self)
     }
 
-    fn stderr(&mut self) -> &mut dyn io::Write {
+    // converts a runtime error into the value bound by a catch clause. errors that are used for
+    // control flow are handed back unchanged since they cannot be caught.
+    fn catch_value(&self, err: Error) -> Result<Value, Error> {
+        match err.into_root() {
+            err @ (Error::Return(_) | Error::Limit(_) | Error::Exit(_)) => Err(err),
+            Error::Throw { value, .. } => Ok(value),
+            err => {
+                let line = err.line().map(|l| Value::Number(l as f64));
+                let fields = HashMap::from([
+                    ("message".to_string(), Value::String(err.to_string())),
+                    ("line".to_string(), line.unwrap_or(Value::Nil)),
+                ]);
+                Ok(Instance::new(self.error_class.clone(), fields).into())
+            }
+        }
+    }
+
+    // attaches the recorded traceback, if any, to an error that escaped to the top level.
+    fn traced(&mut self, err: Error) -> Error {
+        match (self.trace.take(), err.into_root()) {
+            // limit errors are reported on their own since they are not a fault of the script,
+            // and an exit is not a fault at all.
+            (_, err @ (Error::Limit(_) | Error::Exit(_))) => err,
+            (Some(trace), err) => Error::Traced {
+                trace,
+                err: Box::new(err),
+            },
+            (None, err) => err,
+        }
+    }
+
+    pub(crate) fn stdin(&mut self) -> &mut dyn io::BufRead {
+        self.stdin.as_mut()
+    }
+
+    pub(crate) fn stderr(&mut self) -> &mut dyn io::Write {
         self.stderr.as_mut()
     }
 
-    fn stdout(&mut self) -> &mut dyn io::Write {
+    pub(crate) fn stdout(&mut self) -> &mut dyn io::Write {
         self.stdout.as_mut()
     }
 }
 
+// an error raised on behalf of a native, such as a value that can't be hashed, which is given
+// the line the native was called from when there is one.
+fn native_error(err: impl Into<CallableError>, line: Option<usize>) -> Error {
+    let err = err.into();
+    match line {
+        Some(line) => Error::NativeCall { line, err },
+        None => err.into(),
+    }
+}
+
+// the special methods that overload a binary operator for instances: the one called on the left
+// operand, and the reflected one called on the right operand if the left doesn't define its
+// method. comparisons are reflected by swapping them, so `3 < v` calls `v.__gt(3)`, while
+// arithmetic has its own reflected methods, so `3 * v` calls `v.__rmul(3)`. == and != are
+// overloaded by __eq, which values_equal calls.
+fn operator_methods(op: TokenType) -> Option<(&'static str, &'static str)> {
+    use TokenType::*;
+    Some(match op {
+        Plus => ("__add", "__radd"),
+        Minus => ("__sub", "__rsub"),
+        Star => ("__mul", "__rmul"),
+        Slash => ("__div", "__rdiv"),
+        Less => ("__lt", "__gt"),
+        LessEqual => ("__le", "__ge"),
+        Greater => ("__gt", "__lt"),
+        GreaterEqual => ("__ge", "__le"),
+        _ => return None,
+    })
+}
+
 impl StmtVisitor for Interpreter {
     type Output = Result<(), Error>;
     fn visit_expr_stmt(&mut self, expr: &ExprStmt) -> Self::Output {
@@ -158,8 +961,7 @@ impl StmtVisitor for Interpreter {
 
     fn visit_print_stmt(&mut self, expr: &PrintStmt) -> Self::Output {
         let literal = self.evaluate(&expr.expr)?;
-        writeln!(self.stdout(), "{}", literal.to_lox()).map_err(Error::Print)?;
-        Ok(())
+        self.print_value(&literal, Some(expr.keyword.line))
     }
 
     fn visit_var_stmt(&mut self, expr: &VarStmt) -> Self::Output {
@@ -169,6 +971,7 @@ impl StmtVisitor for Interpreter {
             .map(|i| self.evaluate(i))
             .transpose()?
             .unwrap_or(Value::Undefined);
+        self.alloc(expr.name.name().len() + Env::RECORD_SIZE)?;
         self.env.define(&expr.name, value)?;
         Ok(())
     }
@@ -187,18 +990,20 @@ impl StmtVisitor for Interpreter {
 
     fn visit_while_stmt(&mut self, expr: &WhileStmt) -> Self::Output {
         while (self.evaluate(&expr.condition)?.truthy()) {
+            self.budget.step()?;
             self.execute(&expr.body)?;
         }
         Ok(())
     }
 
     fn visit_function_stmt(&mut self, stmt: &FunctionStmt) -> Self::Output {
+        self.alloc(LoxFunction::SIZE)?;
         self.env.define(
             &stmt.name,
-            Value::Function(Function::LoxFunction(LoxFunction {
-                stmt: stmt.clone().into(),
-                closure: self.env.clone(),
-            })),
+            Value::Function(Function::LoxFunction(
+                LoxFunction::new(stmt.clone().into(), self.env.clone())
+                    .in_module(self.module.clone()),
+            )),
         )?;
         Ok(())
     }
@@ -218,18 +1023,60 @@ impl StmtVisitor for Interpreter {
             let Stmt::Function(func_stmt) = method else {
                 return Err(Error::ClassStmtNotFunction);
             };
+            self.alloc(LoxFunction::SIZE)?;
             methods.insert(
-                func_stmt.name.to_string(),
-                LoxFunction {
-                    stmt: func_stmt.clone().into(),
-                    closure: self.env.clone(),
-                },
+                func_stmt.name.name(),
+                LoxFunction::new(func_stmt.clone().into(), self.env.clone())
+                    .in_module(self.module.clone()),
             );
         }
         let class = Class::new(&stmt.name, methods);
         self.env.assign(&stmt.name, class)?;
         Ok(())
     }
+
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) -> Self::Output {
+        let value = self.evaluate(&stmt.value)?;
+        Err(Error::Throw {
+            value,
+            line: stmt.keyword.line,
+        })
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> Self::Output {
+        let mut res = self.execute_block(&stmt.body);
+        if let Some(catch) = &stmt.catch {
+            if let Err(err) = res {
+                res = match self.catch_value(err) {
+                    Ok(value) => {
+                        // the error was handled so its traceback no longer applies
+                        self.trace = None;
+                        self.env.push();
+                        let caught = self
+                            .env
+                            .define(&catch.name, value)
+                            .map_err(Error::from)
+                            .and_then(|_| self.execute_block(&catch.body));
+                        self.env.pop()?;
+                        caught
+                    }
+                    Err(err) => Err(err),
+                };
+            }
+        }
+        if let Some(finally) = &stmt.finally {
+            self.execute_block(finally)?;
+        }
+        res
+    }
+
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Self::Output {
+        let (path, globals) = self.load_module(stmt.path.as_ref(), stmt.keyword.line)?;
+        self.alloc(stmt.name.name().len() + Env::RECORD_SIZE)?;
+        self.env
+            .define(&stmt.name, Module::new(&stmt.name, path, globals))?;
+        Ok(())
+    }
 }
 
 impl ExprVisitor for Interpreter {
@@ -246,6 +1093,17 @@ impl ExprVisitor for Interpreter {
         let left = self.evaluate(&expr.left)?;
         let right = self.evaluate(&expr.right)?;
         let op = &expr.op;
+        // either operand may overload the operator with a special method, and the left one
+        // takes precedence
+        if let Some((name, reflected)) = operator_methods(op.typ) {
+            let line = Some(op.line);
+            if let Some(res) = self.call_special(&left, name, vec![right.clone()], line)? {
+                return Ok(res);
+            }
+            if let Some(res) = self.call_special(&right, reflected, vec![left.clone()], line)? {
+                return Ok(res);
+            }
+        }
         Ok(match op.typ {
             Minus | Slash | Star | Greater | GreaterEqual | Less | LessEqual => {
                 let (Value::Number(left), Value::Number(right)) = (left, right) else {
@@ -269,15 +1127,18 @@ impl ExprVisitor for Interpreter {
             }
             Plus => match (left, right) {
                 (Value::Number(left), Value::Number(right)) => (left + right).into(),
-                (Value::String(left), Value::String(right)) => format!("{left}{right}").into(),
+                (Value::String(left), Value::String(right)) => {
+                    self.alloc(left.len() + right.len())?;
+                    format!("{left}{right}").into()
+                }
                 _ => {
                     return Err(Error::NumbersRequired {
                         op: expr.op.clone(),
                     })
                 }
             },
-            BangEqual => (left != right).into(),
-            EqualEqual => (left == right).into(),
+            BangEqual => (!self.values_equal(&left, &right, Some(op.line))?).into(),
+            EqualEqual => self.values_equal(&left, &right, Some(op.line))?.into(),
             _ => {
                 return Err(Error::InvalidBinaryOp {
                     op: expr.op.clone(),
@@ -317,6 +1178,10 @@ impl ExprVisitor for Interpreter {
         let right = self.evaluate(&expr.right)?;
         Ok(match expr.op.typ {
             TokenType::Minus => {
+                let line = Some(expr.op.line);
+                if let Some(res) = self.call_special(&right, "__neg", vec![], line)? {
+                    return Ok(res);
+                }
                 let Value::Number(right) = &right else {
                     return Err(Error::NumbersRequired {
                         op: expr.op.clone(),
@@ -345,48 +1210,60 @@ impl ExprVisitor for Interpreter {
             .iter()
             .map(|arg| self.evaluate(arg))
             .collect::<Result<Vec<_>, _>>()?;
-        let arity = callable.arity();
-        if args.len() != arity {
-            return Err(Error::FunctionArity {
-                token: expr.paren.clone(),
-                expected: arity,
-                actual: args.len(),
-            });
-        }
-        self.fn_depth += 1;
-        let fn_res = callable.call(self, args);
-        self.fn_depth -= 1;
-        Ok(fn_res?)
+        self.call(callable, args, Some(expr.paren.line))
+    }
+
+    fn visit_this_expr(&mut self, expr: &ThisExpr) -> Self::Output {
+        self.env
+            .lookup(&expr.keyword)
+            .ok_or(Error::ThisOutsideMethod {
+                line: expr.keyword.line,
+            })
     }
 
     fn visit_get_expr(&mut self, expr: &GetExpr) -> Self::Output {
         let object = self.evaluate(&expr.object)?;
-        let Value::Instance(instance) = object else {
-            return Err(Error::OnlyInstancesHaveProperties {
-                token: expr.name.clone(),
-            });
+        let res = match object {
+            Value::Instance(instance) => instance.get(&expr.name),
+            Value::Native(native) => native.get(expr.name.as_ref()),
+            Value::Module(module) => {
+                module
+                    .get(&expr.name)
+                    .ok_or_else(|| InstanceError::UndefinedProperty {
+                        name: expr.name.name(),
+                    })
+            }
+            _ => {
+                return Err(Error::OnlyInstancesHaveProperties {
+                    token: expr.name.clone(),
+                })
+            }
         };
-        Ok(instance
-            .get(&expr.name)
-            .map_err(|err| Error::InstanceError {
-                token: expr.name.clone(),
-                err,
-            })?)
+        res.map_err(|err| Error::InstanceError {
+            token: expr.name.clone(),
+            err,
+        })
     }
 
     fn visit_set_expr(&mut self, expr: &SetExpr) -> Self::Output {
         let object = self.evaluate(&expr.object)?;
-        let Value::Instance(instance) = object else {
+        if !matches!(object, Value::Instance(_) | Value::Native(_)) {
             return Err(Error::OnlyInstancesHaveProperties {
                 token: expr.name.clone(),
             });
-        };
+        }
         let value = self.evaluate(&expr.value)?;
-        Ok(instance
-            .set(&expr.name, value)
-            .map_err(|err| Error::InstanceError {
-                token: expr.name.clone(),
-                err,
-            })?)
+        let res = match object {
+            Value::Instance(instance) => {
+                self.alloc(expr.name.name().len() + Instance::FIELD_SIZE)?;
+                instance.set(&expr.name, value)
+            }
+            Value::Native(native) => native.set(expr.name.as_ref(), value),
+            _ => unreachable!(),
+        };
+        res.map_err(|err| Error::InstanceError {
+            token: expr.name.clone(),
+            err,
+        })
     }
 }
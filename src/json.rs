@@ -0,0 +1,452 @@
+use crate::prelude::*;
+use std::{collections::BTreeMap, fmt::Write};
+
+// nesting deeper than this is rejected so that hostile input cannot overflow the native stack.
+const MAX_DEPTH: usize = 128;
+
+/// The widest indent that json can be pretty printed with, the same as JSON.stringify.
+pub const MAX_INDENT: usize = 10;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum JsonError {
+    #[error("invalid json at line {line}, column {column}: {msg}")]
+    Parse {
+        msg: String,
+        line: usize,
+        column: usize,
+    },
+
+    #[error("cannot encode {0} as json")]
+    Unsupported(&'static str),
+
+    /// The json or the values parsed from it would take more than the specified number of bytes.
+    #[error("json larger than {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Defines the json natives in the interpreter's global scope.
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    int.define_fn("json_parse", 1, |int, args| {
+        let src = args[0].as_str()?;
+        let (value, size) = within_memory(int, |max_size| parse_within(src, max_size))?;
+        int.native_alloc(size)?;
+        Ok(value)
+    });
+    // the indent is the number of spaces to pretty print with, up to MAX_INDENT
+    int.define_fn("json_stringify", Arity::range(1, 2), |int, args| {
+        let indent = match args.get(1) {
+            Some(Value::Nil) | None => None,
+            Some(indent) => {
+                let n = indent.as_number()?;
+                if n.fract() != 0.0 || !(0.0..=MAX_INDENT as f64).contains(&n) {
+                    let msg =
+                        format!("indent must be an integer from 0 to {MAX_INDENT} but got {n}");
+                    return Err(CallableError::Generic(msg.into()));
+                }
+                Some(n as usize)
+            }
+        };
+        let json = within_memory(int, |max_len| stringify_within(&args[0], indent, max_len))?;
+        int.native_alloc(json.len())?;
+        Ok(Value::String(json))
+    });
+}
+
+// runs a parse or encoding that fails with TooLarge past a maximum size, within the memory that
+// is certainly free. if that isn't enough, it is run again once freed memory has been credited
+// back by measuring live memory.
+fn within_memory<T>(
+    int: &mut Interpreter,
+    f: impl Fn(usize) -> Result<T, JsonError>,
+) -> Result<T, CallableError> {
+    let max = int.remaining_memory().unwrap_or(usize::MAX);
+    match f(max) {
+        Err(JsonError::TooLarge(_)) if max < usize::MAX => {
+            let max = int.measure_memory().unwrap_or(usize::MAX);
+            f(max).map_err(|err| json_error(int, err, max))
+        }
+        res => res.map_err(|err| json_error(int, err, max)),
+    }
+}
+
+// converts an error from a native. json that outgrew the remaining memory is charged against
+// the memory limit, so that its own error is raised.
+fn json_error(int: &mut Interpreter, err: JsonError, max: usize) -> CallableError {
+    match err {
+        JsonError::TooLarge(_) => match int.native_alloc(max.saturating_add(1)) {
+            Err(err) => err,
+            Ok(()) => CallableError::Generic(JsonError::TooLarge(max).into()),
+        },
+        err => CallableError::Generic(err.into()),
+    }
+}
+
+/// Parses a json document. Objects become maps and arrays become lists.
+pub fn parse(src: &str) -> Result<Value, JsonError> {
+    parse_within(src, usize::MAX).map(|(value, _)| value)
+}
+
+// parses a document along with the approximate number of bytes allocated for its values,
+// failing with TooLarge if that would be more than max_size.
+fn parse_within(src: &str, max_size: usize) -> Result<(Value, usize), JsonError> {
+    let mut parser = Parser {
+        src,
+        pos: 0,
+        size: 0,
+        max_size,
+    };
+    parser.whitespace();
+    let value = parser.value(0)?;
+    parser.whitespace();
+    if parser.pos < src.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok((value, parser.size))
+}
+
+/// Encodes a value as json, pretty printing it with the specified indent if there is one.
+/// Instances are encoded as objects of their fields. Indents wider than [`MAX_INDENT`] are
+/// narrowed to it.
+pub fn stringify(value: &Value, indent: Option<usize>) -> Result<String, JsonError> {
+    stringify_within(value, indent, usize::MAX)
+}
+
+// encodes a value, failing with TooLarge if the json would be longer than max_len.
+fn stringify_within(
+    value: &Value,
+    indent: Option<usize>,
+    max_len: usize,
+) -> Result<String, JsonError> {
+    let mut writer = Writer {
+        out: String::new(),
+        indent: indent.map(|indent| indent.min(MAX_INDENT)),
+        max_len,
+    };
+    writer.value(value, 0)?;
+    if writer.out.len() > max_len {
+        return Err(JsonError::TooLarge(max_len));
+    }
+    Ok(writer.out)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    // the bytes allocated for the values parsed so far
+    size: usize,
+    max_size: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+        self.alloc(std::mem::size_of::<Value>())?;
+        match self.peek() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => self.string().map(Value::String),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Nil),
+            Some(ch) => Err(self.error(format!("unexpected character '{ch}'"))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect('{')?;
+        let mut entries = BTreeMap::new();
+        self.whitespace();
+        if !self.try_match('}') {
+            loop {
+                self.whitespace();
+                if self.peek() != Some('"') {
+                    return Err(self.error("expected string key"));
+                }
+                let key = self.string()?;
+                self.whitespace();
+                self.expect(':')?;
+                self.whitespace();
+                let value = self.value(depth + 1)?;
+                entries.insert(key, value);
+                self.whitespace();
+                if self.try_match('}') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(Value::Map(Map::new(entries)))
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.expect('[')?;
+        let mut vals = vec![];
+        self.whitespace();
+        if !self.try_match(']') {
+            loop {
+                self.whitespace();
+                vals.push(self.value(depth + 1)?);
+                self.whitespace();
+                if self.try_match(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(Value::List(List::new(vals)))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let Some(ch) = self.advance() else {
+                return Err(self.error("unterminated string"));
+            };
+            match ch {
+                '"' => {
+                    self.alloc(s.len())?;
+                    return Ok(s);
+                }
+                '\\' => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(escaped);
+                }
+                ch if ch.is_control() => return Err(self.error("control character in string")),
+                ch => s.push(ch),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"));
+        }
+        // a high surrogate must be followed by an escaped low surrogate
+        if !(self.try_match('\\') && self.try_match('u')) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        // from_str_radix also accepts a leading sign, so the digits are checked first
+        let digits = self.src.get(self.pos..self.pos + 4);
+        let digits = digits.filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()));
+        let code = digits.and_then(|d| u32::from_str_radix(d, 16).ok());
+        let code = code.ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.pos;
+        self.try_match('-');
+        if !self.try_match('0') && self.digits() == 0 {
+            return Err(self.error("expected digit"));
+        }
+        if self.try_match('.') && self.digits() == 0 {
+            return Err(self.error("expected digit after '.'"));
+        }
+        if self.try_match('e') || self.try_match('E') {
+            if !self.try_match('+') {
+                self.try_match('-');
+            }
+            if self.digits() == 0 {
+                return Err(self.error("expected digit in exponent"));
+            }
+        }
+        let n = self.src[start..self.pos]
+            .parse::<f64>()
+            .map_err(|_| self.error("invalid number"))?;
+        Ok(Value::Number(n))
+    }
+
+    fn digits(&mut self) -> usize {
+        let mut count = 0;
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.pos += 1;
+            count += 1;
+        }
+        count
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        if !self.src[self.pos..].starts_with(word) {
+            return Err(self.error("invalid literal"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn alloc(&mut self, bytes: usize) -> Result<(), JsonError> {
+        self.size = self.size.saturating_add(bytes);
+        if self.size > self.max_size {
+            return Err(JsonError::TooLarge(self.max_size));
+        }
+        Ok(())
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), JsonError> {
+        if self.try_match(ch) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(actual) => Err(self.error(format!("expected '{ch}' but got '{actual}'"))),
+            None => Err(self.error(format!("expected '{ch}' but got end of input"))),
+        }
+    }
+
+    fn try_match(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn error(&self, msg: impl AsRef<str>) -> JsonError {
+        let consumed = &self.src[..self.pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.chars().rev().take_while(|ch| *ch != '\n').count() + 1;
+        JsonError::Parse {
+            msg: msg.as_ref().to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+// encodes values into a string, failing once it grows past a maximum length so that a run's
+// memory limit can stop a huge encoding before it is built.
+struct Writer {
+    out: String,
+    indent: Option<usize>,
+    max_len: usize,
+}
+
+impl Writer {
+    fn value(&mut self, value: &Value, depth: usize) -> Result<(), JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(JsonError::Unsupported("deeply nested value"));
+        }
+        if self.out.len() > self.max_len {
+            return Err(JsonError::TooLarge(self.max_len));
+        }
+        let out = &mut self.out;
+        match value {
+            Value::Nil | Value::Undefined => out.push_str("null"),
+            Value::Bool(b) => write!(out, "{b}").unwrap(),
+            Value::Number(n) if n.is_finite() => write!(out, "{n}").unwrap(),
+            Value::Number(_) => return Err(JsonError::Unsupported("non-finite number")),
+            Value::String(s) => write_string(out, s),
+            Value::List(list) => {
+                let vals = list.values();
+                self.seq('[', ']', &vals, depth, |w, val| w.value(val, depth + 1))?;
+            }
+            Value::Map(map) if map.has_non_string_keys() => {
+                return Err(JsonError::Unsupported("map with non-string keys"))
+            }
+            Value::Map(map) => self.entries(&map.entries(), depth)?,
+            Value::Instance(instance) => {
+                let entries = instance
+                    .fields()
+                    .into_iter()
+                    .sorted_by(|a, b| a.0.cmp(&b.0));
+                self.entries(&entries.collect_vec(), depth)?;
+            }
+            other => return Err(JsonError::Unsupported(other.type_name())),
+        }
+        Ok(())
+    }
+
+    fn entries(&mut self, entries: &[(String, Value)], depth: usize) -> Result<(), JsonError> {
+        let sep = if self.indent.is_some() { ": " } else { ":" };
+        self.seq('{', '}', entries, depth, |w, (key, val)| {
+            write_string(&mut w.out, key);
+            w.out.push_str(sep);
+            w.value(val, depth + 1)
+        })
+    }
+
+    fn seq<T>(
+        &mut self,
+        open: char,
+        close: char,
+        items: &[T],
+        depth: usize,
+        mut write_item: impl FnMut(&mut Self, &T) -> Result<(), JsonError>,
+    ) -> Result<(), JsonError> {
+        self.out.push(open);
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                self.out.push(',');
+            }
+            if let Some(indent) = self.indent {
+                self.out.push('\n');
+                self.out.push_str(&" ".repeat(indent * (depth + 1)));
+            }
+            write_item(self, item)?;
+        }
+        if let (Some(indent), false) = (self.indent, items.is_empty()) {
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(indent * depth));
+        }
+        self.out.push(close);
+        Ok(())
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
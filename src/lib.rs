@@ -1,16 +1,36 @@
 #![allow(unused)]
+// runtime errors carry tokens and values so that they can be reported and caught by scripts.
+#![allow(clippy::result_large_err)]
 
 pub mod class;
+pub mod console;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod env;
 pub mod expr;
+pub mod file;
 pub mod func;
 pub mod instance;
 pub mod interpreter;
+pub mod json;
+pub mod limit;
+pub mod list;
 pub mod lox;
+pub mod map;
+pub mod math;
+pub mod module;
+pub mod native;
 pub mod parser;
 pub mod prelude;
+pub mod process;
+pub mod random;
+pub mod reflect;
 pub mod scanner;
+#[cfg(feature = "serde")]
+pub mod ser;
 pub mod stmt;
+pub mod time;
+pub mod trace;
 pub mod value;
 
 #[cfg(test)]
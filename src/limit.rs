@@ -0,0 +1,174 @@
+use crate::env::Env;
+use crate::prelude::*;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Errors raised when a script exceeds one of the resource limits configured on the interpreter.
+/// Unlike other runtime errors, these cannot be caught by scripts.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum LimitError {
+    #[error("execution budget of {0} steps exceeded")]
+    Steps(u64),
+
+    #[error("execution timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("out of memory: limit of {0} bytes exceeded")]
+    Memory(usize),
+}
+
+/// The resource limits applied to each top-level run of the interpreter.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// the maximum number of steps (loop iterations and function calls) a run may take
+    pub max_steps: Option<u64>,
+    /// the maximum wall-clock time a run may take
+    pub timeout: Option<Duration>,
+    /// the maximum number of bytes of live memory that scripts may use for strings, instances,
+    /// closures and environments
+    pub max_memory: Option<usize>,
+}
+
+/// Tracks the resources used by the current run against the configured limits.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    limits: Limits,
+    steps: u64,
+    deadline: Option<Instant>,
+    // an upper bound of the memory in use: the live memory when it was last measured, plus
+    // everything allocated since. freed memory is only credited back by measuring again.
+    used: usize,
+    // the live memory when the limit was set, which isn't counted against it
+    baseline: usize,
+}
+
+impl Budget {
+    pub(crate) fn limits_mut(&mut self) -> &mut Limits {
+        &mut self.limits
+    }
+
+    /// resets the usage counters at the start of a run. memory stays in use between runs, so the
+    /// memory counter is left as it is.
+    pub(crate) fn reset(&mut self) {
+        self.steps = 0;
+        self.deadline = self.limits.timeout.map(|t| Instant::now() + t);
+    }
+
+    /// records a single step of execution, failing if any limit has been exceeded.
+    pub(crate) fn step(&mut self) -> Result<(), LimitError> {
+        self.steps += 1;
+        if let Some(max) = self.limits.max_steps {
+            if self.steps > max {
+                return Err(LimitError::Steps(max));
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if Instant::now() >= deadline {
+                return Err(LimitError::Timeout(timeout));
+            }
+        }
+        Ok(())
+    }
+
+    /// the time left before this run times out, or None if there is no timeout.
+    pub(crate) fn remaining_time(&self) -> Option<Duration> {
+        let deadline = self.deadline?;
+        Some(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// sets the memory limit, along with the live memory at the time that isn't counted
+    /// against it.
+    pub(crate) fn set_max_memory(&mut self, bytes: usize, baseline: usize) {
+        self.limits.max_memory = Some(bytes);
+        self.baseline = baseline;
+        self.used = 0;
+    }
+
+    /// the number of bytes that can certainly be allocated without exceeding the memory limit,
+    /// or None if there is no limit. more may be available once live memory is measured.
+    pub(crate) fn remaining_memory(&self) -> Option<usize> {
+        let max = self.limits.max_memory?;
+        Some(max.saturating_sub(self.used))
+    }
+
+    /// records an allocation of the specified number of bytes. returns false if the memory in
+    /// use may now exceed the limit, in which case live memory must be measured with
+    /// [`Budget::measured`] to decide whether it does.
+    pub(crate) fn alloc(&mut self, bytes: usize) -> bool {
+        self.used = self.used.saturating_add(bytes);
+        self.limits.max_memory.is_none_or(|max| self.used <= max)
+    }
+
+    /// replaces the estimate of the memory in use with a measurement of live memory, plus an
+    /// allocation that is being made but isn't reachable yet. fails if that exceeds the limit.
+    pub(crate) fn measured(&mut self, live: usize, bytes: usize) -> Result<(), LimitError> {
+        self.used = live.saturating_sub(self.baseline).saturating_add(bytes);
+        match self.limits.max_memory {
+            Some(max) if self.used > max => Err(LimitError::Memory(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Measures the memory reachable from the values and scopes that are added to it, using the
+/// same sizes that allocations are charged with. Shared objects are only counted once, and
+/// nested objects are visited from a worklist rather than recursively, so that deeply nested
+/// values can't overflow the stack.
+#[derive(Default)]
+pub(crate) struct Meter {
+    bytes: usize,
+    seen: HashSet<(usize, usize)>,
+    pending: Vec<Pending>,
+}
+
+enum Pending {
+    Value(Value),
+    Env(Env),
+}
+
+impl Meter {
+    pub(crate) fn add(&mut self, bytes: usize) {
+        self.bytes = self.bytes.saturating_add(bytes);
+    }
+
+    /// Returns whether this is the first visit to a shared object. The key distinguishes
+    /// objects that share an allocation, such as closures created from the same declaration.
+    pub(crate) fn first_visit<T: ?Sized>(&mut self, rc: &Rc<T>, key: usize) -> bool {
+        self.seen
+            .insert((Rc::as_ptr(rc) as *const () as usize, key))
+    }
+
+    /// Counts a value, and everything reachable from it once [`Meter::finish`] is called.
+    pub(crate) fn value(&mut self, value: &Value) {
+        match value {
+            Value::String(s) => self.add(s.len()),
+            Value::Number(_) | Value::Bool(_) | Value::Nil | Value::Undefined => {}
+            other => self.pending.push(Pending::Value(other.clone())),
+        }
+    }
+
+    /// Counts a scope and its parents once [`Meter::finish`] is called.
+    pub(crate) fn env(&mut self, env: &Env) {
+        self.pending.push(Pending::Env(env.clone()));
+    }
+
+    /// Visits everything reachable from what was added, returning the total number of bytes.
+    pub(crate) fn finish(mut self) -> usize {
+        while let Some(next) = self.pending.pop() {
+            match next {
+                Pending::Env(env) => env.measure(&mut self),
+                Pending::Value(value) => match value {
+                    Value::Function(Function::LoxFunction(f)) => f.measure(&mut self),
+                    Value::Class(c) => c.measure(&mut self),
+                    Value::Instance(i) => i.measure(&mut self),
+                    Value::List(l) => l.measure(&mut self),
+                    Value::Map(m) => m.measure(&mut self),
+                    Value::Native(n) => n.measure(&mut self),
+                    Value::Module(m) => m.measure(&mut self),
+                    _ => {}
+                },
+            }
+        }
+        self.bytes
+    }
+}
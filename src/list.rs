@@ -0,0 +1,75 @@
+use crate::prelude::*;
+use std::fmt::Display;
+
+/// The runtime representation of a lox list. Lists are shared by reference, so clones observe
+/// the same underlying values, but compare by value: `==` compares their elements in turn,
+/// including with the `equals` methods of instances.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct List {
+    inner: Rc<RefCell<Vec<Value>>>,
+}
+
+impl List {
+    pub fn new(vals: Vec<Value>) -> Self {
+        let inner = Rc::new(RefCell::new(vals));
+        Self { inner }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.as_ref().borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether two lists are the same list, rather than lists with equal values.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn get(&self, idx: usize) -> Option<Value> {
+        self.inner.as_ref().borrow().get(idx).cloned()
+    }
+
+    pub fn push(&self, val: Value) {
+        self.inner.as_ref().borrow_mut().push(val);
+    }
+
+    /// Swaps the values at two indexes, panicking if either is out of bounds.
+    pub fn swap(&self, a: usize, b: usize) {
+        self.inner.as_ref().borrow_mut().swap(a, b);
+    }
+
+    /// Counts the list and queues its values.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if !meter.first_visit(&self.inner, 0) {
+            return;
+        }
+        let Ok(vals) = self.inner.as_ref().try_borrow() else {
+            return;
+        };
+        meter.add(vals.len() * std::mem::size_of::<Value>());
+        for val in vals.iter() {
+            meter.value(val);
+        }
+    }
+
+    /// Returns a copy of the values currently in the list.
+    pub fn values(&self) -> Vec<Value> {
+        self.inner.as_ref().borrow().clone()
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(vals: Vec<Value>) -> Self {
+        Self::new(vals)
+    }
+}
+
+impl Display for List {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let vals = self.inner.as_ref().borrow();
+        write!(f, "[{}]", vals.iter().join(", "))
+    }
+}
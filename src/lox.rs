@@ -1,7 +1,7 @@
 use crate::prelude::*;
 use anyhow::bail;
 use itertools::Itertools;
-use std::{fmt::Display, io};
+use std::{fmt::Display, io, time::Duration};
 use tracing_subscriber::field::display;
 
 #[derive(thiserror::Error, Debug)]
@@ -11,7 +11,32 @@ pub enum LoxError {
     #[error(transparent)]
     Parse(#[from] ParseError),
     #[error(transparent)]
-    Interpret(#[from] interpreter::Error),
+    Interpret(interpreter::Error),
+    /// A resource limit configured on the builder was exceeded. The [`LimitError`] says which.
+    #[error(transparent)]
+    Limit(LimitError),
+    /// Scripts used more live memory than the limit set with [`Lox::max_memory`].
+    #[error("out of memory: limit of {limit} bytes exceeded")]
+    OutOfMemory { limit: usize },
+    #[error("exited with code {0}")]
+    Exit(u8),
+    #[error("could not read {}: {err}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+}
+
+impl From<interpreter::Error> for LoxError {
+    fn from(err: interpreter::Error) -> Self {
+        match err {
+            interpreter::Error::Limit(LimitError::Memory(limit)) => Self::OutOfMemory { limit },
+            interpreter::Error::Limit(err) => Self::Limit(err),
+            interpreter::Error::Exit(code) => Self::Exit(code),
+            err => Self::Interpret(err),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -29,18 +54,46 @@ impl Lox {
         let tokens = scanner.scan_tokens().map_err(LoxError::Scan)?;
         // Parser::parse should take a &[Token] instead.
         if let Ok(expr) = parser::Parser::new(tokens.clone()).single_expr() {
-            let val = self.interpreter.evaluate(&expr)?;
-            println!("{val}");
+            let line = tokens.first().map(|token| token.line);
+            self.interpreter.interpret_and_print(&expr, line)?;
         } else {
             let mut parser = parser::Parser::new(tokens);
             let stmts = parser.parse().map_err(LoxError::Parse)?;
-            self.interpreter
-                .interpret(&stmts)
-                .map_err(LoxError::Interpret)?;
+            self.interpreter.interpret(&stmts)?;
         }
         Ok(())
     }
 
+    /// Runs the script at the specified path, read with the module loader. Imports in the script
+    /// are resolved relative to the directory that contains it.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), LoxError> {
+        let path = path.as_ref();
+        let prog = self
+            .interpreter
+            .load_script(path)
+            .map_err(|err| LoxError::Io {
+                path: path.to_path_buf(),
+                err,
+            })?;
+        self.run(prog)
+    }
+
+    /// Looks up a global defined by the prelude or by a previous run.
+    pub fn get_global(&self, name: impl AsRef<str>) -> Option<Value> {
+        self.interpreter.get_global(name)
+    }
+
+    /// Calls a global function or class with args supplied by the host.
+    pub fn call(&mut self, name: impl AsRef<str>, args: Vec<Value>) -> Result<Value, LoxError> {
+        Ok(self.interpreter.call_global(name, args)?)
+    }
+
+    /// Sets the reader that the `input` and `read_line` natives read from.
+    pub fn stdin(mut self, r: impl io::BufRead + 'static) -> Self {
+        self.interpreter = self.interpreter.with_stdin(Box::new(r));
+        self
+    }
+
     pub fn stdout(mut self, w: impl Into<Box<dyn io::Write>>) -> Self {
         self.interpreter = self.interpreter.with_stdout(w.into());
         self
@@ -50,4 +103,126 @@ impl Lox {
         self.interpreter = self.interpreter.with_stderr(w.into());
         self
     }
+
+    /// Sets the maximum number of nested function calls, which defaults to [`DEFAULT_MAX_DEPTH`].
+    /// Exceeding it raises a stack overflow error that scripts can catch, or that fails the run
+    /// with [`LoxError::Interpret`] if they don't.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.interpreter = self.interpreter.with_max_depth(depth);
+        self
+    }
+
+    /// Sets the number of steps that each run may take, where every loop iteration and function
+    /// call is a step. Unlimited by default. Exceeding it fails the run with [`LoxError::Limit`]
+    /// of [`LimitError::Steps`], which scripts can't catch.
+    pub fn max_steps(mut self, steps: u64) -> Self {
+        self.interpreter = self.interpreter.with_max_steps(steps);
+        self
+    }
+
+    /// Sets the wall-clock time that each run may take, which is checked at every step.
+    /// Unlimited by default. Exceeding it fails the run with [`LoxError::Limit`] of
+    /// [`LimitError::Timeout`], which scripts can't catch.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.interpreter = self.interpreter.with_timeout(timeout);
+        self
+    }
+
+    /// Registers a native function that scripts can call by name. Arguments can be converted into
+    /// rust types with helpers such as [`Value::as_number`] or `f64::try_from`, and functions
+    /// passed in by the script can be called with [`Interpreter::call_value`].
+    pub fn register_fn(
+        mut self,
+        name: impl AsRef<str>,
+        arity: impl Into<Arity>,
+        func: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, CallableError> + 'static,
+    ) -> Self {
+        self.interpreter.define_fn(name, arity, func);
+        self
+    }
+
+    /// Registers a class whose instances are values of a rust type, made by the specified
+    /// constructor. Scripts see it as a class, so `type` and `instanceof` work with it, and the
+    /// values are exposed to scripts through their [`NativeObject`] implementation.
+    pub fn register_class<T: NativeObject>(
+        mut self,
+        name: impl AsRef<str>,
+        arity: impl Into<Arity>,
+        ctor: impl Fn(&mut Interpreter, Vec<Value>) -> Result<T, CallableError> + 'static,
+    ) -> Self {
+        let ctor = NativeFunction::new(name.as_ref(), arity, move |int, args| {
+            int.native_alloc(std::mem::size_of::<T>())?;
+            let obj = ctor(int, args)?;
+            Ok(Value::Native(NativeInstance::new(obj)))
+        });
+        let class = Class::native(ctor);
+        self.interpreter.define_builtin(name, Value::Class(class));
+        self
+    }
+
+    /// Sets the command line args that scripts see as the global `args` list.
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let args = args.into_iter().map(Into::into).collect();
+        self.interpreter = self.interpreter.with_args(args);
+        self
+    }
+
+    /// Sets the environment variables that scripts can read with `getenv`, such as
+    /// `std::env::vars()` or an allowed subset of them. Scripts see no variables by default.
+    pub fn env_vars(
+        mut self,
+        vars: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        let vars = vars
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        self.interpreter = self.interpreter.with_env_vars(vars);
+        self
+    }
+
+    /// Seeds the generator used by the `random` module so that runs are reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.interpreter = self.interpreter.with_seed(seed);
+        self
+    }
+
+    /// Sets the source of the current time for scripts, such as a [`ManualClock`] that tests
+    /// can freeze. Defaults to [`SystemClock`].
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.interpreter = self.interpreter.with_clock(Box::new(clock));
+        self
+    }
+
+    /// Sets the dirs that scripts may read and write with the file natives. Scripts have no
+    /// file access unless it is granted here. Once a policy is set, scripts can only import
+    /// modules from the filesystem that are within a dir it allows reading.
+    pub fn file_policy(mut self, policy: FilePolicy) -> Self {
+        self.interpreter = self.interpreter.with_file_policy(policy);
+        self
+    }
+
+    /// Sets the maximum number of bytes of live memory that scripts may use for strings,
+    /// instances, closures, and scopes. Memory that scripts no longer refer to is credited back,
+    /// and memory already in use when the limit is set isn't counted. Unlimited by default.
+    /// Exceeding it fails the run with [`LoxError::OutOfMemory`].
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.interpreter = self.interpreter.with_max_memory(bytes);
+        self
+    }
+
+    /// Sets the loader that scripts and their imports are read with, such as a [`MemoryLoader`]
+    /// of scripts embedded in the binary. Defaults to [`FsLoader`].
+    pub fn loader(mut self, loader: impl ModuleLoader + 'static) -> Self {
+        self.interpreter = self.interpreter.with_loader(Box::new(loader));
+        self
+    }
+
+    /// Sets the dirs that imports are resolved against when they are not found relative to the
+    /// importing script.
+    pub fn search_path(mut self, dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        let dirs = dirs.into_iter().map(Into::into).collect();
+        self.interpreter = self.interpreter.with_search_path(dirs);
+        self
+    }
 }
@@ -3,43 +3,71 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use rox::prelude::*;
+use std::process::ExitCode;
 
 #[derive(Debug, clap::Parser)]
 struct Args {
     script: Option<PathBuf>,
+    /// Args passed to the script as the `args` list
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+    /// A dir that scripts may read files from
+    #[arg(long)]
+    allow_read: Vec<PathBuf>,
+    /// A dir that scripts may read and write files in
+    #[arg(long)]
+    allow_write: Vec<PathBuf>,
+    /// Lets scripts read the environment variables of the process with getenv
+    #[arg(long)]
+    allow_env: bool,
 }
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let args = Args::parse();
     tracing_subscriber::fmt().init();
+    let mut lox = Lox::new().args(args.args);
+    if args.allow_env {
+        lox = lox.env_vars(std::env::vars());
+    }
+    // a policy also restricts imports, so one is only set if file access was asked for
+    if !args.allow_read.is_empty() || !args.allow_write.is_empty() {
+        let mut policy = FilePolicy::new();
+        for dir in &args.allow_read {
+            policy = policy.allow_read(dir);
+        }
+        for dir in &args.allow_write {
+            policy = policy.allow_write(dir);
+        }
+        lox = lox.file_policy(policy);
+    }
     if let Some(script) = args.script {
-        run_file(&script)?
+        run_file(lox, &script)
     } else {
-        run_prompt()?
+        run_prompt(lox)
     }
-    Ok(())
 }
 
-fn run_file(script: &Path) -> Result<()> {
-    let mut lox = Lox::new();
-    let bs = fs::read(script)?;
-    let prog = String::from_utf8(bs).context("script to utf8")?;
-    lox.run(prog).map_err(|err| anyhow::anyhow!("lox: {err}"))?;
-    Ok(())
+fn run_file(mut lox: Lox, script: &Path) -> Result<ExitCode> {
+    match lox.run_file(script) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(LoxError::Exit(code)) => Ok(ExitCode::from(code)),
+        Err(err) => Err(anyhow::anyhow!("lox: {err}")),
+    }
 }
 
-fn run_prompt() -> Result<()> {
-    let mut lox = Lox::new();
+fn run_prompt(mut lox: Lox) -> Result<ExitCode> {
     for line in stdin().lines() {
         let line = line?;
         if line.is_empty() {
             continue;
         }
-        if let Err(err) = lox.run(line) {
-            eprintln!("{err}");
+        match lox.run(line) {
+            Ok(()) => {}
+            Err(LoxError::Exit(code)) => return Ok(ExitCode::from(code)),
+            Err(err) => eprintln!("{err}"),
         }
     }
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 fn run(prog: String) -> Result<()> {
@@ -0,0 +1,281 @@
+use crate::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{Debug, Display},
+};
+
+/// The runtime representation of a lox map. Like lists, maps are shared by reference.
+///
+/// String keys are kept in order. Keys of other types are hashed and compared by the
+/// interpreter, so that instances can define their own `equals` and `hash` methods, and are kept
+/// in the order they were inserted.
+///
+/// Maps are equal only if they are the same map, since a map may contain itself.
+#[derive(Clone, Default)]
+pub struct Map {
+    inner: Rc<RefCell<Inner>>,
+}
+
+#[derive(Clone, Default)]
+struct Inner {
+    strings: BTreeMap<String, Value>,
+    keyed: Vec<(Value, Value)>,
+    // the indexes into keyed of the entries with each key hash
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+impl Map {
+    pub fn new(entries: BTreeMap<String, Value>) -> Self {
+        let inner = Inner {
+            strings: entries,
+            ..Default::default()
+        };
+        let inner = Rc::new(RefCell::new(inner));
+        Self { inner }
+    }
+
+    pub fn len(&self) -> usize {
+        let inner = self.inner.as_ref().borrow();
+        inner.strings.len() + inner.keyed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: impl AsRef<str>) -> Option<Value> {
+        self.inner
+            .as_ref()
+            .borrow()
+            .strings
+            .get(key.as_ref())
+            .cloned()
+    }
+
+    pub fn insert(&self, key: impl AsRef<str>, val: Value) -> Option<Value> {
+        self.inner
+            .as_ref()
+            .borrow_mut()
+            .strings
+            .insert(key.as_ref().to_string(), val)
+    }
+
+    /// Looks up a key of any type, returning an error if the key can't be hashed or if calling
+    /// one of its methods fails.
+    pub fn lookup(&self, int: &mut Interpreter, key: &Value) -> Result<Option<Value>, Error> {
+        if let Value::String(key) = key {
+            return Ok(self.get(key));
+        }
+        let found = self.find(int, key)?.1;
+        Ok(found.map(|idx| self.inner.as_ref().borrow().keyed[idx].1.clone()))
+    }
+
+    /// Inserts a value under a key of any type, replacing the value of an equal key.
+    pub fn set(&self, int: &mut Interpreter, key: Value, val: Value) -> Result<(), Error> {
+        if let Value::String(key) = key {
+            self.insert(key, val);
+            return Ok(());
+        }
+        let (hash, found) = self.find(int, &key)?;
+        let mut inner = self.inner.as_ref().borrow_mut();
+        match found {
+            Some(idx) => inner.keyed[idx].1 = val,
+            None => {
+                let idx = inner.keyed.len();
+                inner.keyed.push((key, val));
+                inner.buckets.entry(hash).or_default().push(idx);
+            }
+        }
+        Ok(())
+    }
+
+    // finds the hash of a key and the index of the entry with an equal key, if there is one.
+    // the candidates are copied out first since comparing them may call back into lox code that
+    // uses the map. errors are reported on the line of the native that is using the map.
+    fn find(&self, int: &mut Interpreter, key: &Value) -> Result<(u64, Option<usize>), Error> {
+        let line = int.call_line();
+        let hash = int.hash_value(key, line)?;
+        let candidates = {
+            let inner = self.inner.as_ref().borrow();
+            let idxs = inner.buckets.get(&hash).into_iter().flatten();
+            idxs.map(|&idx| (idx, inner.keyed[idx].0.clone()))
+                .collect_vec()
+        };
+        for (idx, candidate) in candidates {
+            if int.values_equal(key, &candidate, line)? {
+                return Ok((hash, Some(idx)));
+            }
+        }
+        Ok((hash, None))
+    }
+
+    /// Counts the map and its entries, and queues their keys and values.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if !meter.first_visit(&self.inner, 0) {
+            return;
+        }
+        let Ok(inner) = self.inner.as_ref().try_borrow() else {
+            return;
+        };
+        let entry_size = std::mem::size_of::<(Value, Value)>();
+        meter.add(std::mem::size_of::<Inner>());
+        for (key, value) in &inner.strings {
+            meter.add(key.len() + entry_size);
+            meter.value(value);
+        }
+        for (key, value) in &inner.keyed {
+            meter.add(entry_size);
+            meter.value(key);
+            meter.value(value);
+        }
+    }
+
+    /// Inserts a value under a string, number, bool or nil key, replacing the value of an equal
+    /// key. Unlike [`Map::set`], this doesn't need an interpreter, since keys of those types
+    /// don't have methods.
+    pub fn insert_primitive(&self, key: Value, val: Value) -> Result<Option<Value>, ValueError> {
+        let Some(hash) = hash_primitive(&key) else {
+            return Err(ValueError::Custom(format!(
+                "map keys must be strings, numbers, bools or nil but got {}",
+                key.type_name()
+            )));
+        };
+        if let Value::String(key) = key {
+            return Ok(self.insert(key, val));
+        }
+        let mut inner = self.inner.as_ref().borrow_mut();
+        let idxs = inner.buckets.get(&hash).into_iter().flatten();
+        if let Some(&idx) = idxs.clone().find(|&&idx| inner.keyed[idx].0 == key) {
+            return Ok(Some(std::mem::replace(&mut inner.keyed[idx].1, val)));
+        }
+        let idx = inner.keyed.len();
+        inner.keyed.push((key, val));
+        inner.buckets.entry(hash).or_default().push(idx);
+        Ok(None)
+    }
+
+    /// Returns a copy of all of the entries currently in the map: those with string keys ordered
+    /// by key, followed by the rest in the order they were inserted.
+    pub fn all_entries(&self) -> Vec<(Value, Value)> {
+        let inner = self.inner.as_ref().borrow();
+        let strings = inner
+            .strings
+            .iter()
+            .map(|(k, v)| (Value::String(k.clone()), v.clone()));
+        strings.chain(inner.keyed.iter().cloned()).collect()
+    }
+
+    /// Returns a copy of the entries with string keys currently in the map, ordered by key.
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        let inner = self.inner.as_ref().borrow();
+        inner
+            .strings
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Whether the map has any keys that are not strings, which can't be converted into formats
+    /// such as json.
+    pub fn has_non_string_keys(&self) -> bool {
+        !self.inner.as_ref().borrow().keyed.is_empty()
+    }
+}
+
+// hashes a number, which is consistent with ==.
+pub(crate) fn hash_number(n: f64) -> u64 {
+    // -0 == 0 so both must have the same hash
+    if n == 0.0 {
+        0
+    } else {
+        n.to_bits()
+    }
+}
+
+// hashes a key that is compared by value without calling into lox code, or returns None if the
+// key is of another type.
+pub(crate) fn hash_primitive(key: &Value) -> Option<u64> {
+    match key {
+        Value::Number(n) => Some(hash_number(*n)),
+        Value::Bool(b) => Some(*b as u64),
+        Value::Nil => Some(0),
+        Value::String(s) => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            s.hash(&mut hasher);
+            Some(hasher.finish())
+        }
+        _ => None,
+    }
+}
+
+impl FromIterator<(String, Value)> for Map {
+    fn from_iter<T: IntoIterator<Item = (String, Value)>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+thread_local! {
+    // the maps that are currently being displayed, so that a map that contains itself is shown
+    // as {...} instead of recursing forever.
+    static DISPLAYING: RefCell<HashSet<*const RefCell<Inner>>> = RefCell::default();
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ptr = Rc::as_ptr(&self.inner);
+        if !DISPLAYING.with_borrow_mut(|displaying| displaying.insert(ptr)) {
+            return write!(f, "{{...}}");
+        }
+        let res = {
+            let inner = self.inner.as_ref().borrow();
+            let strings = inner.strings.iter().map(|(k, v)| format!(r#""{k}": {v}"#));
+            let keyed = inner.keyed.iter().map(|(k, v)| format!("{k}: {v}"));
+            write!(f, "{{{}}}", strings.chain(keyed).join(", "))
+        };
+        DISPLAYING.with_borrow_mut(|displaying| displaying.remove(&ptr));
+        res
+    }
+}
+
+impl Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Map({self})")
+    }
+}
+
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    int.define_fn("map", 0, |int, _| {
+        int.native_alloc(std::mem::size_of::<Inner>())?;
+        Ok(Value::Map(Map::default()))
+    });
+    int.define_fn("map_get", 2, |int, args| {
+        let found = args[0]
+            .as_map()?
+            .lookup(int, &args[1])
+            .map_err(|err| CallableError::Call(err.into()))?;
+        Ok(found.unwrap_or(Value::Nil))
+    });
+    int.define_fn("map_has", 2, |int, args| {
+        let found = args[0]
+            .as_map()?
+            .lookup(int, &args[1])
+            .map_err(|err| CallableError::Call(err.into()))?;
+        Ok(found.is_some().into())
+    });
+    int.define_fn("map_set", 3, |int, mut args| {
+        let val = args.pop().expect("arity is 3");
+        let key = args.pop().expect("arity is 3");
+        let map = args[0].as_map()?;
+        int.native_alloc(std::mem::size_of::<(Value, Value)>())?;
+        map.set(int, key, val)
+            .map_err(|err| CallableError::Call(err.into()))?;
+        Ok(Value::Nil)
+    });
+}
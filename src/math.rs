@@ -0,0 +1,61 @@
+use crate::prelude::*;
+
+/// Defines the built-in `math` module. Every function checks that its args are numbers and
+/// returns a number, apart from `is_nan` which returns a bool.
+pub(crate) fn define_module(int: &mut Interpreter) {
+    let bindings = vec![
+        constant("pi", std::f64::consts::PI),
+        constant("e", std::f64::consts::E),
+        constant("inf", f64::INFINITY),
+        constant("nan", f64::NAN),
+        unary("sqrt", f64::sqrt),
+        unary("abs", f64::abs),
+        unary("floor", f64::floor),
+        unary("ceil", f64::ceil),
+        // rounds half way cases away from zero
+        unary("round", f64::round),
+        unary("sin", f64::sin),
+        unary("cos", f64::cos),
+        unary("tan", f64::tan),
+        // the natural logarithm
+        unary("log", f64::ln),
+        unary("exp", f64::exp),
+        function("pow", 2, |args| {
+            Ok(args[0].as_number()?.powf(args[1].as_number()?).into())
+        }),
+        function("is_nan", 1, |args| Ok(args[0].as_number()?.is_nan().into())),
+        // min and max propagate nan rather than ignoring it, unlike f64::min and f64::max.
+        function("min", Arity::at_least(1), |args| fold(&args, f64::min)),
+        function("max", Arity::at_least(1), |args| fold(&args, f64::max)),
+    ];
+    int.define_module("math", bindings);
+}
+
+fn constant(name: &str, value: f64) -> (String, Value) {
+    (name.to_string(), Value::Number(value))
+}
+
+fn unary(name: &str, f: fn(f64) -> f64) -> (String, Value) {
+    function(name, 1, move |args| Ok(f(args[0].as_number()?).into()))
+}
+
+fn function(
+    name: &str,
+    arity: impl Into<Arity>,
+    f: impl Fn(Vec<Value>) -> Result<Value, CallableError> + 'static,
+) -> (String, Value) {
+    NativeFunction::new(name, arity, move |_, args| f(args)).binding()
+}
+
+fn fold(args: &[Value], f: fn(f64, f64) -> f64) -> Result<Value, CallableError> {
+    let mut acc = args[0].as_number()?;
+    for arg in &args[1..] {
+        let n = arg.as_number()?;
+        acc = if acc.is_nan() || n.is_nan() {
+            f64::NAN
+        } else {
+            f(acc, n)
+        };
+    }
+    Ok(acc.into())
+}
@@ -0,0 +1,193 @@
+use crate::env::Env;
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io;
+use std::path::Component;
+
+/// The runtime representation of an imported lox module. A module's exports are the bindings
+/// in the global env that its script was executed in.
+#[derive(Clone, Debug)]
+pub struct Module {
+    inner: Rc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    name: String,
+    path: Option<PathBuf>,
+    globals: Env,
+}
+
+impl Module {
+    pub(crate) fn new(name: impl AsRef<str>, path: impl Into<PathBuf>, globals: Env) -> Self {
+        let inner = Rc::new(Inner {
+            name: name.as_ref().to_string(),
+            path: Some(path.into()),
+            globals,
+        });
+        Self { inner }
+    }
+
+    /// Creates a built-in module of values supplied by the host, such as native functions.
+    pub fn builtin(
+        name: impl AsRef<str>,
+        bindings: impl IntoIterator<Item = (String, Value)>,
+    ) -> Self {
+        let mut globals = Env::default();
+        for (name, value) in bindings {
+            globals
+                .define(name, value)
+                .expect("globals allow redefinition");
+        }
+        let inner = Rc::new(Inner {
+            name: name.as_ref().to_string(),
+            path: None,
+            globals,
+        });
+        Self { inner }
+    }
+
+    /// The name the module was imported as.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The resolved path of the module's script, or None for a built-in module.
+    pub fn path(&self) -> Option<&Path> {
+        self.inner.path.as_deref()
+    }
+
+    /// Queues the module's globals to be counted.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if meter.first_visit(&self.inner, 0) {
+            meter.env(&self.inner.globals);
+        }
+    }
+
+    /// Looks up an exported binding by name.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<Value> {
+        self.inner.globals.lookup(name)
+    }
+}
+
+// modules are shared by reference and only ever equal to themselves. comparing their globals
+// would recurse through every closure defined in the module.
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.inner.name)
+    }
+}
+
+/// Locates and reads the source of modules for `import` statements.
+pub trait ModuleLoader {
+    /// Resolves a candidate path to the path that identifies the module, which is used to only
+    /// execute each module once. Returns a [`io::ErrorKind::NotFound`] error if there is no module
+    /// at the path.
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Reads the source of a module from a path returned by [`ModuleLoader::resolve`].
+    fn load(&self, path: &Path) -> io::Result<String>;
+
+    /// Whether the resolved paths are paths on the filesystem. If so, imports are checked
+    /// against the interpreter's [`FilePolicy`] when one has been set.
+    fn on_filesystem(&self) -> bool {
+        false
+    }
+}
+
+/// Loads modules from the filesystem. This is the default loader.
+#[derive(Clone, Debug, Default)]
+pub struct FsLoader;
+
+impl ModuleLoader for FsLoader {
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn on_filesystem(&self) -> bool {
+        true
+    }
+}
+
+/// Loads modules from an in-memory map of paths to sources, such as scripts embedded in a
+/// binary. Paths are compared after resolving `.` and `..` components.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLoader {
+    modules: HashMap<PathBuf, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.modules.insert(normalize(path.as_ref()), source.into());
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no module at {}", path.display()),
+        )
+    }
+}
+
+impl<P: AsRef<Path>, S: Into<String>> FromIterator<(P, S)> for MemoryLoader {
+    fn from_iter<T: IntoIterator<Item = (P, S)>>(iter: T) -> Self {
+        let mut loader = Self::new();
+        for (path, source) in iter {
+            loader.insert(path, source);
+        }
+        loader
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = normalize(path);
+        if self.modules.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(Self::not_found(&path))
+        }
+    }
+
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.modules
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Self::not_found(path))
+    }
+}
+
+// lexically resolves the . and .. components of a path. a .. that would leave a relative path
+// is kept so that the result won't alias a different path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normal.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normal.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normal.push(".."),
+            },
+            other => normal.push(other),
+        }
+    }
+    normal
+}
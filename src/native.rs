@@ -0,0 +1,171 @@
+use crate::prelude::*;
+use std::{any::Any, fmt::Display};
+
+/// This is the trait that rust types must implement to be exposed to scripts as objects.
+/// Property access and method calls on the object are dispatched to it by name.
+pub trait NativeObject: Any {
+    /// The name of the class that the object is an instance of.
+    fn class_name(&self) -> &str;
+
+    /// Returns the value of a property, or None if there is no such property.
+    fn get(&self, name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Sets the value of a property, returning false if there is no such property.
+    fn set(&mut self, name: &str, value: Value) -> Result<bool, ValueError> {
+        Ok(false)
+    }
+
+    /// Returns the arity of a method, or None if there is no such method.
+    fn method_arity(&self, name: &str) -> Option<Arity> {
+        None
+    }
+
+    /// Calls a method. This is only called for methods that have an arity.
+    ///
+    /// The object is only borrowed immutably while its method runs, so that lox code the method
+    /// calls back into can still read its properties. Methods that change the object need
+    /// interior mutability, such as a `Cell` or `RefCell` field.
+    fn call_method(
+        &self,
+        int: &mut Interpreter,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, CallableError> {
+        Err(CallableError::Generic(
+            format!("no method '{name}' on {}", self.class_name()).into(),
+        ))
+    }
+}
+
+/// The runtime representation of a rust value that has been exposed to scripts.
+#[derive(Clone)]
+pub struct NativeInstance {
+    inner: Rc<RefCell<dyn NativeObject>>,
+    // the class that made the object, if it was made by a class registered by the host
+    class: Option<Class>,
+}
+
+impl NativeInstance {
+    pub fn new(obj: impl NativeObject) -> Self {
+        let inner = Rc::new(RefCell::new(obj));
+        Self { inner, class: None }
+    }
+
+    // the object as an instance of the class that made it.
+    pub(crate) fn with_class(self, class: Class) -> Self {
+        let class = Some(class);
+        Self { class, ..self }
+    }
+
+    /// The class that made the object, or None if it was made by the host directly.
+    pub fn class(&self) -> Option<Class> {
+        self.class.clone()
+    }
+
+    /// Counts the wrapped value. Any memory that it owns on the heap is not counted.
+    pub(crate) fn measure(&self, meter: &mut Meter) {
+        if meter.first_visit(&self.inner, 0) {
+            meter.add(std::mem::size_of_val(self.inner.as_ref()));
+        }
+        if let Some(class) = &self.class {
+            meter.value(&Value::Class(class.clone()));
+        }
+    }
+
+    /// Calls the function with a reference to the wrapped value if it is a T. Returns None if
+    /// the value is not a T or is being mutated.
+    pub fn with<T: NativeObject, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let obj = self.inner.as_ref().try_borrow().ok()?;
+        let any: &dyn Any = &*obj;
+        any.downcast_ref::<T>().map(f)
+    }
+
+    /// Calls the function with a mutable reference to the wrapped value if it is a T. Returns
+    /// None if the value is not a T or is already borrowed, such as while one of its own methods
+    /// runs.
+    pub fn with_mut<T: NativeObject, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut obj = self.inner.as_ref().try_borrow_mut().ok()?;
+        let any: &mut dyn Any = &mut *obj;
+        any.downcast_mut::<T>().map(f)
+    }
+
+    /// Looks up a property, binding the object to a method of the same name if there is no
+    /// such property.
+    pub fn get(&self, name: &str) -> Result<Value, InstanceError> {
+        let obj = self.inner.as_ref().try_borrow().map_err(|_| in_use(name))?;
+        if let Some(val) = obj.get(name) {
+            return Ok(val);
+        }
+        let Some(arity) = obj.method_arity(name) else {
+            return Err(InstanceError::UndefinedProperty {
+                name: name.to_string(),
+            });
+        };
+        let this = self.clone();
+        let method = name.to_string();
+        let native = NativeFunction::new(name, arity, move |int, args| {
+            let obj = this
+                .inner
+                .as_ref()
+                .try_borrow()
+                .map_err(|_| in_use(&method))?;
+            obj.call_method(int, &method, args)
+        });
+        Ok(Value::Function(Function::Native(native)))
+    }
+
+    pub fn set(&self, name: &str, value: Value) -> Result<Value, InstanceError> {
+        let found = self
+            .inner
+            .as_ref()
+            .try_borrow_mut()
+            .map_err(|_| in_use(name))?
+            .set(name, value)
+            .map_err(|err| InstanceError::InvalidProperty {
+                name: name.to_string(),
+                err,
+            })?;
+        if !found {
+            return Err(InstanceError::UndefinedProperty {
+                name: name.to_string(),
+            });
+        }
+        Ok(Value::Nil)
+    }
+}
+
+// the error for accessing a native object while it is borrowed, such as setting a property from
+// inside one of its own methods, which borrow it for the duration of the call.
+fn in_use(name: &str) -> CallableError {
+    CallableError::Generic(format!("'{name}' accessed while its object is in use").into())
+}
+
+impl PartialEq for NativeInstance {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl std::fmt::Debug for NativeInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.inner.as_ref().try_borrow() {
+            Ok(obj) => f
+                .debug_struct("NativeInstance")
+                .field("class", &obj.class_name())
+                .finish(),
+            Err(_) => f.debug_struct("NativeInstance").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl Display for NativeInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.inner.as_ref().try_borrow() {
+            Ok(obj) => write!(f, "{} instance", obj.class_name()),
+            // the object is being mutated by one of its methods, so its class can't be read
+            Err(_) => write!(f, "native instance"),
+        }
+    }
+}
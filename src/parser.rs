@@ -1,5 +1,3 @@
-use std::io::{self, stderr};
-
 use crate::prelude::*;
 type TT = TokenType;
 
@@ -7,13 +5,12 @@ pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     errs: Vec<LineError>,
-    stderr: Box<dyn io::Write>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
-    #[error("parsing failed")]
-    Failed,
+    #[error("{}", .errs.iter().join("\n"))]
+    Failed { errs: Vec<LineError> },
 
     #[error("single expr required")]
     SingleEpxr,
@@ -39,6 +36,18 @@ pub enum LineError {
     #[error("line {}: too many params (max: 255)", token.line)]
     TooManyParams { token: Token },
 
+    #[error("line {}: param '{}' without a default follows a param with one", token.line, token.lexeme)]
+    RequiredAfterDefault { token: Token },
+
+    #[error("line {}: rest param '{}' must be the last param", token.line, token.lexeme)]
+    RestNotLast { token: Token },
+
+    #[error("line {line}: expected 'catch' or 'finally' after try block")]
+    TryWithoutHandler { line: usize },
+
+    #[error("line {line}: expected 'as' after import path")]
+    ImportWithoutAs { line: usize },
+
     #[error("({kind}): {err}")]
     FunctionKind {
         kind: FunctionKind,
@@ -54,6 +63,17 @@ pub enum LineError {
     },
 }
 
+impl ParseError {
+    /// Returns the line errors that caused parsing to fail, in the order they were encountered.
+    pub fn errors(&self) -> &[LineError] {
+        match self {
+            Self::Failed { errs } => errs,
+            Self::LineError(err) => std::slice::from_ref(err),
+            Self::SingleEpxr => &[],
+        }
+    }
+}
+
 trait LineResultExt<T> {
     fn context(self, ctx: impl AsRef<str>) -> Result<T, LineError>;
     fn for_fn_kind(self, fk: FunctionKind) -> Result<T, LineError>;
@@ -62,7 +82,7 @@ trait LineResultExt<T> {
 impl<T> LineResultExt<T> for Result<T, LineError> {
     /// extends a LineError result with additional context info
     fn context(self, ctx: impl AsRef<str>) -> Result<T, LineError> {
-        self.map_err(|err| err.context(ctx.as_ref().to_string()))
+        self.map_err(|err| err.context(ctx.as_ref()))
     }
     /// adds additional context about the kind of fn
     fn for_fn_kind(self, kind: FunctionKind) -> Result<T, LineError> {
@@ -101,33 +121,28 @@ impl Parser {
             tokens,
             current: 0,
             errs: vec![],
-            stderr: Box::new(stderr()),
         }
     }
 
+    /// Parses all of the tokens into statements. If any errors are encountered, the parser
+    /// synchronizes to the next statement and keeps going so that every error can be returned.
     pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut stmts = vec![];
-        let mut failed = false;
         loop {
             if self.at_end() {
                 break;
             }
             match self.decl() {
-                Ok(stmt) => {
-                    if !failed {
-                        stmts.push(stmt);
-                    }
-                }
-
+                Ok(stmt) => stmts.push(stmt),
                 Err(err) => {
-                    tracing::error!("parse: {err}");
-                    failed = true;
+                    self.errs.push(err);
                     self.synchronize();
                 }
             }
         }
-        if failed || !self.errs.is_empty() {
-            Err(ParseError::Failed)
+        if !self.errs.is_empty() {
+            let errs = std::mem::take(&mut self.errs);
+            Err(ParseError::Failed { errs })
         } else {
             Ok(stmts)
         }
@@ -155,8 +170,11 @@ impl Parser {
                     | TT::For
                     | TT::Fun
                     | TT::If
+                    | TT::Import
                     | TT::Print
                     | TT::Return
+                    | TT::Throw
+                    | TT::Try
                     | TT::Var
                     | TT::While
             ) {
@@ -176,9 +194,32 @@ impl Parser {
         if self.match_any(TT::Class) {
             return self.class_decl();
         }
+        if self.match_any(TT::Import) {
+            return self.import_decl();
+        }
         self.stmt()
     }
 
+    // import_decl → "import" STRING "as" IDENTIFIER ";" ;
+    fn import_decl(&mut self) -> Result<Stmt, LineError> {
+        let keyword = self.previous();
+        let path = self.consume(TT::String)?;
+        // 'as' is only a keyword here, so it can still be used as a name elsewhere
+        if !(self.check(TT::Identifier) && self.peek().lexeme.as_ref() == "as") {
+            return Err(LineError::ImportWithoutAs {
+                line: self.peek().line,
+            });
+        }
+        self.advance();
+        let name = self.consume(TT::Identifier)?;
+        self.consume(TT::Semicolon)?;
+        Ok(Stmt::Import(ImportStmt {
+            keyword,
+            path,
+            name,
+        }))
+    }
+
     fn class_decl(&mut self) -> Result<Stmt, LineError> {
         let ident = self.consume(TT::Identifier)?;
         self.consume(TT::LeftBrace)
@@ -198,10 +239,26 @@ impl Parser {
     fn function(&mut self, kind: FunctionKind) -> Result<Stmt, LineError> {
         let name = self.consume(TT::Identifier).for_fn_kind(kind)?;
         self.consume(TT::LeftParen).for_fn_kind(kind)?;
-        let mut params = vec![];
+        let mut params: Vec<Param> = vec![];
+        let mut rest = None;
         if !self.check(TT::RightParen) {
             loop {
-                params.push(self.consume(TT::Identifier)?);
+                if self.match_any(TT::Ellipsis) {
+                    let token = self.consume(TT::Identifier)?;
+                    if !self.check(TT::RightParen) {
+                        return Err(LineError::RestNotLast { token });
+                    }
+                    rest.replace(token);
+                    break;
+                }
+                let name = self.consume(TT::Identifier)?;
+                let default = self.match_any(TT::Equal).then(|| self.expr()).transpose()?;
+                if default.is_none() && params.last().is_some_and(|p| p.default.is_some()) {
+                    self.smol_error(LineError::RequiredAfterDefault {
+                        token: name.clone(),
+                    });
+                }
+                params.push(Param { name, default });
                 if !self.match_any(TT::Comma) {
                     break;
                 }
@@ -213,7 +270,12 @@ impl Parser {
         self.consume(TT::RightParen).for_fn_kind(kind)?;
         self.consume(TT::LeftBrace)?;
         let body = self.block()?;
-        Ok(Stmt::Function(FunctionStmt { name, params, body }))
+        Ok(Stmt::Function(FunctionStmt {
+            name,
+            params,
+            rest,
+            body,
+        }))
     }
 
     fn var_decl(&mut self) -> Result<Stmt, LineError> {
@@ -233,6 +295,12 @@ impl Parser {
         if self.match_any(TT::Return) {
             return self.return_stmt();
         }
+        if self.match_any(TT::Throw) {
+            return self.throw_stmt();
+        }
+        if self.match_any(TT::Try) {
+            return self.try_stmt();
+        }
         if self.match_any(TT::While) {
             return self.while_stmt();
         }
@@ -256,6 +324,46 @@ impl Parser {
         Ok(Stmt::Return(ReturnStmt { keyword, value }))
     }
 
+    fn throw_stmt(&mut self) -> Result<Stmt, LineError> {
+        let keyword = self.previous();
+        let value = self.expr()?;
+        self.consume(TT::Semicolon)?;
+        Ok(Stmt::Throw(ThrowStmt { keyword, value }))
+    }
+
+    // try_stmt → "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )? ;
+    fn try_stmt(&mut self) -> Result<Stmt, LineError> {
+        let keyword = self.previous();
+        self.consume(TT::LeftBrace)
+            .context("expect '{' after try")?;
+        let body = self.block()?;
+        let mut catch = None;
+        if self.match_any(TT::Catch) {
+            self.consume(TT::LeftParen)
+                .context("expect '(' after catch")?;
+            let name = self.consume(TT::Identifier)?;
+            self.consume(TT::RightParen)?;
+            self.consume(TT::LeftBrace)
+                .context("expect '{' before catch body")?;
+            let body = self.block()?;
+            catch.replace(CatchClause { name, body });
+        }
+        let mut finally = None;
+        if self.match_any(TT::Finally) {
+            self.consume(TT::LeftBrace)
+                .context("expect '{' after finally")?;
+            finally.replace(self.block()?);
+        }
+        if catch.is_none() && finally.is_none() {
+            return Err(LineError::TryWithoutHandler { line: keyword.line });
+        }
+        Ok(Stmt::Try(TryStmt {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
     fn for_stmt(&mut self) -> Result<Stmt, LineError> {
         self.consume(TT::LeftParen)?;
         let init: Option<Stmt> = if self.match_any(TT::Semicolon) {
@@ -323,9 +431,10 @@ impl Parser {
     }
 
     fn print_stmt(&mut self) -> Result<Stmt, LineError> {
+        let keyword = self.previous();
         let expr = self.expr()?;
         self.consume(TT::Semicolon)?;
-        Ok(Stmt::Print(PrintStmt { expr }))
+        Ok(Stmt::Print(PrintStmt { keyword, expr }))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, LineError> {
@@ -375,14 +484,15 @@ impl Parser {
                 }
                 // here we convert a GetExpr into a SetExpr since an '=' follows it.
                 Expr::Get(GetExpr { object, name }) => {
+                    let value = self.assignment()?;
                     return Ok(Expr::from(SetExpr {
                         object: object.clone(),
                         name: name.clone(),
-                        value: expr.into(),
+                        value: value.into(),
                     }));
                 }
                 _ => {
-                    return dbg!(Err(self.expected_typ_error(TT::Equal)));
+                    return Err(self.expected_typ_error(TT::Equal));
                 }
             }
         }
@@ -513,13 +623,13 @@ impl Parser {
         }))
     }
 
+    // records an error that does not require the parser to synchronize
     fn smol_error(&mut self, err: LineError) {
-        error!("{err}");
         self.errs.push(err)
     }
 
-    // primary → NUMBER | STRING | "true" | "false" | "nil"
-    //           | "(" expression ")" ;
+    // primary → NUMBER | STRING | "true" | "false" | "nil" | "this"
+    //           | IDENTIFIER | "(" expression ")" ;
     fn primary(&mut self) -> Result<Expr, LineError> {
         if self.match_any(TT::False) {
             return Ok(Expr::literal(Value::Bool(false)));
@@ -534,6 +644,10 @@ impl Parser {
             let prev = self.previous();
             return Ok(Expr::literal(prev.literal.unwrap()));
         }
+        if self.match_any(TT::This) {
+            let keyword = self.previous();
+            return Ok(Expr::This(ThisExpr { keyword }));
+        }
         if self.match_any(TT::Identifier) {
             let name = self.previous();
             return Ok(Expr::Var(VarExpr { name }));
@@ -1,13 +1,24 @@
 pub use crate::*;
 pub use class::*;
+#[cfg(feature = "serde")]
+pub use de::from_value;
 pub use expr::*;
+pub use file::{Access, FileError, FilePolicy};
 pub use func::*;
 pub use instance::*;
 pub use interpreter::*;
 pub use itertools::Itertools;
+pub use limit::*;
+pub use list::*;
 pub use lox::*;
+pub use map::*;
+pub use module::*;
+pub use native::*;
 pub use parser::*;
+pub use random::Rng;
 pub use scanner::*;
+#[cfg(feature = "serde")]
+pub use ser::to_value;
 pub use std::cell::RefCell;
 pub use std::rc::Rc;
 pub use std::{
@@ -16,5 +27,7 @@ pub use std::{
     path::{Path, PathBuf},
 };
 pub use stmt::*;
+pub use time::{Clock, DateTime, ManualClock, SystemClock};
+pub use trace::*;
 pub use tracing::{debug, error, info, warn};
 pub use value::*;
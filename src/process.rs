@@ -0,0 +1,25 @@
+use crate::prelude::*;
+
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    int.define_builtin("args", Value::List(List::default()));
+    // returns nil if the host has not passed the variable to the interpreter
+    int.define_fn("getenv", 1, |int, args| {
+        let value = int.env_var(args[0].as_str()?).map(str::to_string);
+        Ok(value.map(Value::String).unwrap_or(Value::Nil))
+    });
+    // ends the run with an exit code, which defaults to 0. finally blocks still run as the
+    // exit unwinds, but it can't be caught.
+    int.define_fn("exit", Arity::range(0, 1), |_, args| {
+        let code = match args.first() {
+            Some(code) => code.as_number()?,
+            None => 0.0,
+        };
+        if code.fract() != 0.0 || !(0.0..=255.0).contains(&code) {
+            let msg = format!("exit code must be an integer from 0 to 255 but got {code}");
+            return Err(ValueError::Custom(msg).into());
+        }
+        Err(CallableError::Call(Box::new(interpreter::Error::Exit(
+            code as u8,
+        ))))
+    });
+}
@@ -0,0 +1,103 @@
+use crate::prelude::*;
+
+/// The largest magnitude of an integer that can be represented exactly by a lox number.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// A SplitMix64 pseudo random number generator. Its output for a given seed is fixed, so scripts
+/// that seed it produce the same results on every platform and release. The first value
+/// generated from a seed of 0 is `0xe220a8397b1dcdaf`, the reference output of the algorithm.
+///
+/// The generator is not suitable for cryptographic use.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded from the system clock, for runs that don't need to be
+    /// reproducible.
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range [0, 1) made from the top 53 bits of the next value.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns an integer in the range [0, n), taken as the next value modulo n.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_time()
+    }
+}
+
+/// Defines the built-in `random` module, which draws from the interpreter's [`Rng`].
+pub(crate) fn define_module(int: &mut Interpreter) {
+    let bindings = [
+        NativeFunction::new("random", 0, |int, _| Ok(int.rng().next_f64().into())),
+        // returns an integer between lo and hi inclusive
+        NativeFunction::new("random_int", 2, |int, args| {
+            let (lo, hi) = (integer(&args[0])?, integer(&args[1])?);
+            if lo > hi {
+                return Err(ValueError::Custom(format!("empty range {lo} to {hi}")).into());
+            }
+            let n = int.rng().below(hi.abs_diff(lo) + 1);
+            Ok(Value::Number((lo + n as i64) as f64))
+        }),
+        // shuffles a list in place with the Fisher-Yates algorithm, from the last index down
+        NativeFunction::new("shuffle", 1, |int, args| {
+            let list = args[0].as_list()?;
+            for i in (1..list.len()).rev() {
+                let j = int.rng().below(i as u64 + 1) as usize;
+                list.swap(i, j);
+            }
+            Ok(Value::Nil)
+        }),
+        NativeFunction::new("choice", 1, |int, args| {
+            let list = args[0].as_list()?;
+            if list.is_empty() {
+                return Err(ValueError::Custom("cannot choose from an empty list".into()).into());
+            }
+            let i = int.rng().below(list.len() as u64) as usize;
+            Ok(list.get(i).expect("index is in bounds"))
+        }),
+        NativeFunction::new("seed", 1, |int, args| {
+            *int.rng() = Rng::new(integer(&args[0])? as u64);
+            Ok(Value::Nil)
+        }),
+    ];
+    int.define_module("random", bindings.map(NativeFunction::binding));
+}
+
+// converts a number that is exactly representable as an integer.
+fn integer(value: &Value) -> Result<i64, ValueError> {
+    let n = value.as_number()?;
+    if n.fract() != 0.0 || n.abs() > MAX_SAFE_INTEGER {
+        return Err(ValueError::Custom(format!(
+            "expected an integer but got {n}"
+        )));
+    }
+    Ok(n as i64)
+}
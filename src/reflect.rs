@@ -0,0 +1,43 @@
+use crate::prelude::*;
+
+pub(crate) fn define_natives(int: &mut Interpreter) {
+    int.define_fn("type", 1, |_, args| Ok(args[0].type_name().into()));
+    int.define_fn("instanceof", 2, |_, args| {
+        let Value::Class(class) = &args[1] else {
+            return Err(args[1].type_error("class").into());
+        };
+        let is = match &args[0] {
+            Value::Instance(i) => i.class() == *class,
+            Value::Native(n) => n.class().as_ref() == Some(class),
+            _ => false,
+        };
+        Ok(is.into())
+    });
+    // lists the names of the fields set on an instance in sorted order
+    int.define_fn("fields", 1, |int, args| {
+        let Value::Instance(instance) = &args[0] else {
+            return Err(args[0].type_error("instance").into());
+        };
+        let names = instance.fields().into_keys().sorted().map(Value::String);
+        let names = names.collect_vec();
+        int.native_alloc(names.len() * std::mem::size_of::<Value>())?;
+        Ok(Value::List(names.into()))
+    });
+    // the number of args a function or class takes, or a map of the "min" and "max" if it takes a
+    // range of args. the max is nil if the function is variadic.
+    int.define_fn("arity", 1, |_, args| {
+        let callable = args[0]
+            .as_callable()
+            .ok_or_else(|| args[0].type_error("function"))?;
+        let arity = callable.arity();
+        if arity.max == Some(arity.min) {
+            return Ok((arity.min as f64).into());
+        }
+        let max = arity.max.map(|max| Value::Number(max as f64));
+        let map = Map::from_iter([
+            ("min".to_string(), Value::Number(arity.min as f64)),
+            ("max".to_string(), max.unwrap_or(Value::Nil)),
+        ]);
+        Ok(Value::Map(map))
+    });
+}
@@ -69,7 +69,15 @@ impl Scanner {
             '{' => self.add_token(LeftBrace),
             '}' => self.add_token(RightBrace),
             ',' => self.add_token(Comma),
-            '.' => self.add_token(Dot),
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.advance();
+                    self.advance();
+                    self.add_token(Ellipsis);
+                } else {
+                    self.add_token(Dot);
+                }
+            }
             '-' => self.add_token(Minus),
             '+' => self.add_token(Plus),
             ';' => self.add_token(Semicolon),
@@ -284,19 +292,24 @@ impl Lexeme {
         use TokenType::*;
         match self.0.as_str() {
             "and" => And,
+            "catch" => Catch,
             "class" => Class,
             "else" => Else,
             "false" => False,
+            "finally" => Finally,
             "for" => For,
             "fun" => Fun,
             "if" => If,
+            "import" => Import,
             "nil" => Nil,
             "or" => Or,
             "print" => Print,
             "return" => Return,
             "super" => Super,
             "this" => This,
+            "throw" => Throw,
             "true" => True,
+            "try" => Try,
             "var" => Var,
             "while" => While,
             _ => Identifier,
@@ -362,6 +375,7 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    Ellipsis,
 
     // literals
     Identifier,
@@ -370,19 +384,24 @@ pub enum TokenType {
 
     // keywords
     And,
+    Catch,
     Class,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
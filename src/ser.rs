@@ -0,0 +1,334 @@
+use crate::prelude::*;
+use serde::ser::{self, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Converts a rust value into a lox value. Structs become instances of a class with the same
+/// name, sequences become lists, and maps become maps.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ValueError> {
+    value.serialize(Serializer)
+}
+
+impl ser::Error for ValueError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = ValueError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = VariantSerializer<StructSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+        Ok(Value::Number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+        let vals = v.iter().map(|b| Value::Number(f64::from(*b)));
+        Ok(Value::List(List::new(vals.collect())))
+    }
+
+    fn serialize_none(self) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ValueError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        let map = Map::default();
+        map.insert(variant, to_value(value)?);
+        Ok(Value::Map(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, ValueError> {
+        Ok(SeqSerializer {
+            vals: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, ValueError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, ValueError> {
+        Ok(MapSerializer::default())
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer, ValueError> {
+        Ok(StructSerializer {
+            name,
+            fields: HashMap::default(),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, ValueError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_struct(variant, len)?,
+        })
+    }
+}
+
+struct SeqSerializer {
+    vals: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.vals.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::List(List::new(self.vals)))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+#[derive(Default)]
+struct MapSerializer {
+    map: Map,
+    key: Option<Value>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        self.key.replace(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ValueError::Custom("map value serialized before its key".into()))?;
+        self.map.insert_primitive(key, to_value(value)?)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::Map(self.map))
+    }
+}
+
+struct StructSerializer {
+    name: &'static str,
+    fields: HashMap<String, Value>,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.fields.insert(key.to_string(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        let class = Class::new(self.name, HashMap::default());
+        Ok(Value::Instance(Instance::new(class, self.fields)))
+    }
+}
+
+/// Enum variants with data are represented as a map from the variant name to the data, the same
+/// as serde's default externally tagged representation.
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &str, value: Value) -> Value {
+        let map = Map::default();
+        map.insert(variant, value);
+        Value::Map(map)
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        let value = ser::SerializeSeq::end(self.inner)?;
+        Ok(Self::wrap(self.variant, value))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<StructSerializer> {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        let value = ser::SerializeStruct::end(self.inner)?;
+        Ok(Self::wrap(self.variant, value))
+    }
+}
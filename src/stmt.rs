@@ -18,6 +18,9 @@ pub enum Stmt {
     Function(FunctionStmt),
     Return(ReturnStmt),
     Class(ClassStmt),
+    Throw(ThrowStmt),
+    Try(TryStmt),
+    Import(ImportStmt),
 }
 
 stmt! {
@@ -27,6 +30,7 @@ pub struct ExprStmt {
 
 stmt! {
 pub struct PrintStmt {
+    pub keyword: Token,
     pub expr: Expr,
 }}
 
@@ -54,10 +58,16 @@ stmt! {pub struct WhileStmt {
 
 stmt! {pub struct FunctionStmt {
     pub name: Token,
-    pub params: Vec<Token>,
+    pub params: Vec<Param>,
+    pub rest: Option<Token>,
     pub body: Vec<Stmt>,
 }}
 
+stmt! {pub struct Param {
+    pub name: Token,
+    pub default: Option<Expr>,
+}}
+
 stmt! {pub struct ReturnStmt {
     pub keyword: Token,
     pub value: Expr,
@@ -68,6 +78,28 @@ stmt! {pub struct ClassStmt {
     pub methods :Vec<Stmt>,
 }}
 
+stmt! {pub struct ThrowStmt {
+    pub keyword: Token,
+    pub value: Expr,
+}}
+
+stmt! {pub struct TryStmt {
+    pub body: Vec<Stmt>,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<Vec<Stmt>>,
+}}
+
+stmt! {pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}}
+
+stmt! {pub struct ImportStmt {
+    pub keyword: Token,
+    pub path: Token,
+    pub name: Token,
+}}
+
 impl Stmt {
     pub fn accept<Out>(&self, visitor: &mut impl StmtVisitor<Output = Out>) -> Out {
         match self {
@@ -80,6 +112,9 @@ impl Stmt {
             Stmt::Function(s) => visitor.visit_function_stmt(s),
             Stmt::Return(s) => visitor.visit_return_stmt(s),
             Stmt::Class(s) => visitor.visit_class_stmt(s),
+            Stmt::Throw(s) => visitor.visit_throw_stmt(s),
+            Stmt::Try(s) => visitor.visit_try_stmt(s),
+            Stmt::Import(s) => visitor.visit_import_stmt(s),
         }
     }
 }
@@ -96,4 +131,7 @@ pub trait StmtVisitor {
     fn visit_function_stmt(&mut self, stmt: &FunctionStmt) -> Self::Output;
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::Output;
     fn visit_class_stmt(&mut self, stmt: &ClassStmt) -> Self::Output;
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) -> Self::Output;
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) -> Self::Output;
+    fn visit_import_stmt(&mut self, stmt: &ImportStmt) -> Self::Output;
 }
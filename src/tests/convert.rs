@@ -0,0 +1,139 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Order {
+    id: u32,
+    customer: String,
+    items: Vec<Item>,
+    tags: BTreeMap<String, bool>,
+    note: Option<String>,
+    status: Status,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Item {
+    sku: String,
+    price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Open,
+    Shipped { carrier: String },
+}
+
+fn order() -> Order {
+    Order {
+        id: 7,
+        customer: "ada".to_string(),
+        items: vec![
+            Item {
+                sku: "a1".to_string(),
+                price: 2.5,
+            },
+            Item {
+                sku: "b2".to_string(),
+                price: 10.0,
+            },
+        ],
+        tags: BTreeMap::from([("gift".to_string(), true)]),
+        note: None,
+        status: Status::Shipped {
+            carrier: "ups".to_string(),
+        },
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let order = order();
+    let value = to_value(&order).unwrap();
+    let Value::Instance(instance) = &value else {
+        panic!("expected an instance but got {value}");
+    };
+    assert_eq!(instance.to_string(), "Order instance");
+    assert_eq!(instance.get("id").unwrap(), Value::Number(7.0));
+    assert_eq!(
+        instance.get("tags").unwrap().to_string(),
+        r#"{"gift": true}"#
+    );
+    assert_eq!(from_value::<Order>(value).unwrap(), order);
+
+    let open = to_value(&Status::Open).unwrap();
+    assert_eq!(open, Value::String("Open".to_string()));
+    assert_eq!(from_value::<Status>(open).unwrap(), Status::Open);
+}
+
+#[test]
+fn test_non_string_keys() {
+    let scores = HashMap::from([(1u32, "one".to_string()), (20, "twenty".to_string())]);
+    let value = to_value(&scores).unwrap();
+    let Value::Map(map) = &value else {
+        panic!("expected a map but got {value}");
+    };
+    assert!(map.has_non_string_keys());
+    let mut int = Interpreter::default();
+    assert_eq!(
+        map.lookup(&mut int, &Value::Number(20.0)).unwrap(),
+        Some(Value::String("twenty".to_string()))
+    );
+    assert_eq!(from_value::<HashMap<u32, String>>(value).unwrap(), scores);
+
+    let flags = BTreeMap::from([(true, 1.5), (false, 0.5)]);
+    let value = to_value(&flags).unwrap();
+    assert_eq!(value.to_string(), "{false: 0.5, true: 1.5}");
+    assert_eq!(from_value::<BTreeMap<bool, f64>>(value).unwrap(), flags);
+
+    // keys set by scripts convert too
+    let mut lox = Lox::default();
+    lox.run(r#"var m = map(); map_set(m, 3, "c"); map_set(m, 4, "d");"#)
+        .unwrap();
+    let m: HashMap<u32, String> = from_value(lox.get_global("m").unwrap()).unwrap();
+    assert_eq!(
+        m,
+        HashMap::from([(3, "c".to_string()), (4, "d".to_string())])
+    );
+}
+
+#[test]
+fn test_script_values() {
+    let mut lox = Lox::default();
+    let prog = r#"
+        fun total(order) {
+            return order.items;
+        }
+        class Item {}
+        fun make_item(sku, price) {
+            var item = Item();
+            item.sku = sku;
+            item.price = price;
+            return item;
+        }
+    "#;
+    lox.run(prog).unwrap();
+    let items = lox
+        .call("total", vec![to_value(&order()).unwrap()])
+        .unwrap();
+    assert_eq!(items.to_string(), "[Item instance, Item instance]");
+    let items: Vec<Item> = from_value(items).unwrap();
+    assert_eq!(items, order().items);
+
+    let item = lox
+        .call("make_item", vec!["c3".into(), 1.0.into()])
+        .unwrap();
+    let item: Item = from_value(item).unwrap();
+    assert_eq!(
+        item,
+        Item {
+            sku: "c3".to_string(),
+            price: 1.0
+        }
+    );
+    let err = from_value::<Item>(Value::Number(1.0)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: integer `1`, expected struct Item"
+    );
+}
@@ -0,0 +1,82 @@
+use super::{interpreter::Buffer, TempDir};
+use crate::prelude::*;
+
+#[test]
+fn test_file_natives() {
+    let dir = TempDir::new("file");
+    dir.write("data/in.txt", "one\ntwo\n");
+    dir.write("secret.txt", "hunter2");
+    fs::create_dir_all(dir.join("out")).unwrap();
+
+    let stdout = Buffer::default();
+    let policy = FilePolicy::new()
+        .allow_read(dir.join("data"))
+        .allow_write(dir.join("out"));
+    let mut lox = Lox::default().stdout(stdout.clone()).file_policy(policy);
+    let prog = format!(
+        r#"
+        var dir = "{}";
+        print read_lines(dir + "/data/in.txt");
+        write_file(dir + "/out/new.txt", "a");
+        append_file(dir + "/out/new.txt", "b");
+        print read_file(dir + "/out/new.txt");
+        print exists(dir + "/out/new.txt");
+        print exists(dir + "/data/missing.txt");
+        "#,
+        dir.display()
+    );
+    lox.run(prog).unwrap();
+    assert_eq!(stdout.to_string(), "[\"one\", \"two\"]\nab\ntrue\nfalse\n");
+
+    for (src, ex) in [
+        ("read_file(dir + \"/secret.txt\");", "read access denied"),
+        (
+            "read_file(dir + \"/data/../secret.txt\");",
+            "read access denied",
+        ),
+        ("exists(dir + \"/secret.txt\");", "read access denied"),
+        (
+            "write_file(dir + \"/data/in.txt\", \"x\");",
+            "write access denied",
+        ),
+        ("read_file(dir + \"/data/missing.txt\");", "No such file"),
+    ] {
+        let err = lox.run(src).unwrap_err();
+        assert!(err.to_string().contains(ex), "{err}");
+    }
+    assert_eq!(
+        fs::read_to_string(dir.join("data/in.txt")).unwrap(),
+        "one\ntwo\n"
+    );
+
+    // a dangling symlink can't be used to create a file outside of the allowed dir
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(dir.join("outside.txt"), dir.join("out/link")).unwrap();
+        let err = lox
+            .run(r#"write_file(dir + "/out/link", "escaped");"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("write access denied"), "{err}");
+        assert!(!dir.join("outside.txt").exists());
+    }
+
+    // files are checked against the memory limit before they are read
+    dir.write("data/big.txt", &"x".repeat(1 << 16));
+    let policy = FilePolicy::new().allow_read(dir.join("data"));
+    let mut lox = Lox::default().file_policy(policy).max_memory(1 << 12);
+    for native in ["read_file", "read_lines"] {
+        let err = lox
+            .run(format!(r#"{native}("{}/data/big.txt");"#, dir.display()))
+            .unwrap_err();
+        assert!(matches!(err, LoxError::OutOfMemory { .. }), "{err:?}");
+    }
+
+    let err = Lox::default()
+        .run("read_file(\"Cargo.toml\");")
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("read access denied for Cargo.toml"),
+        "{err}"
+    );
+}
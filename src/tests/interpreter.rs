@@ -1,9 +1,10 @@
 use std::{
     borrow::BorrowMut,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt::Display,
     io::{self, Cursor, Write},
     rc::Rc,
+    time::Duration,
 };
 
 use tracing_test::traced_test;
@@ -44,11 +45,37 @@ fn test_double_define() {
     let err = run_prog(prog).unwrap_err();
     assert!(
         err.to_string()
-            .contains("call: a binding 'a' already exists in this scope"),
+            .ends_with("in bad\na binding 'a' already exists in this scope"),
         "{err}"
     );
 }
 
+#[test]
+fn test_traceback() {
+    let prog = r#"
+        fun inner(n) {
+            return n / 0;
+        }
+        fun outer(n) {
+            return inner(n) + 1;
+        }
+        print "before";
+        outer(1);
+    "#;
+    let err = run_prog(prog).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        [
+            "Traceback (most recent call last):",
+            "  line 8, in <script>",
+            "  line 5, in outer",
+            "  line 2, in inner",
+            "divide by zero detected at line 2",
+        ]
+        .join("\n")
+    );
+}
+
 #[test]
 fn test_global_return() {
     let prog = r#"
@@ -117,6 +144,1291 @@ fn test_object_properties() {
     assert_eq!(run.lines(), vec!["42"]);
 }
 
+#[test]
+fn test_try_catch() {
+    let prog = r#"
+        try {
+            print "before";
+            print 1 / 0;
+            print "unreachable";
+        } catch (e) {
+            print e.message;
+            print e.line;
+        } finally {
+            print "finally";
+        }
+        try {
+            throw "oops";
+        } catch (e) {
+            print e;
+        }
+        class Empty {}
+        try {
+            Empty().missing;
+        } catch (e) {
+            print e.message;
+        }
+        try {
+            json_parse("{");
+        } catch (e) {
+            print e.line;
+        }
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "before",
+            "divide by zero detected at line 3",
+            "3",
+            "finally",
+            "oops",
+            "line 18: undefined property 'missing'",
+            "23",
+        ]
+    );
+}
+
+#[test]
+fn test_try_finally() {
+    let prog = r#"
+        fun f() {
+            try {
+                return "returned";
+            } finally {
+                print "cleanup";
+            }
+        }
+        print f();
+        fun g() {
+            throw "inner";
+        }
+        try {
+            try {
+                g();
+            } finally {
+                print "inner finally";
+            }
+        } catch (e) {
+            print "caught " + e;
+        }
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec!["cleanup", "returned", "inner finally", "caught inner"]
+    );
+}
+
+#[test]
+fn test_uncaught_throw() {
+    let prog = r#"
+        try {
+            throw "first";
+        } catch (e) {
+            throw e + " again";
+        }
+    "#;
+    let err = run_prog(prog).unwrap_err();
+    assert_eq!(err.to_string(), "line 4: uncaught exception: first again");
+}
+
+#[test]
+fn test_stack_overflow() {
+    let prog = r#"
+        fun recurse(n) {
+            return recurse(n + 1);
+        }
+        try {
+            recurse(0);
+        } catch (e) {
+            print e.message;
+        }
+        recurse(0);
+    "#;
+    let err = run_prog(prog).unwrap_err();
+    assert!(err.to_string().ends_with("line 2: Stack overflow"), "{err}");
+    assert!(err.to_string().contains("  line 9, in <script>"), "{err}");
+    // the recursive frames are collapsed into a single entry
+    assert_eq!(err.to_string().matches("in recurse").count(), 1, "{err}");
+    assert!(
+        err.to_string()
+            .contains("  line 2, in recurse\n  ... previous frame repeated"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_native_callback_recursion() {
+    // natives that call back into the script are subject to the same depth limit
+    let mut lox =
+        Lox::default().register_fn("apply", 1, |int, args| int.call_value(&args[0], vec![]));
+    let prog = r#"
+        fun recurse() {
+            return apply(recurse);
+        }
+        recurse();
+    "#;
+    let err = lox.run(prog).unwrap_err();
+    assert!(err.to_string().ends_with("Stack overflow"), "{err}");
+    assert!(err.to_string().contains("in apply"), "{err}");
+
+    let err = lox.run("apply(1);").unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("expected function but got number"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_deep_recursion() {
+    // scripts that recursed this deep before the depth limit was added must keep working with
+    // the default limit, even on a test thread's small stack.
+    let prog = r#"
+        fun count(n) {
+            if (n == 0) return 0;
+            return count(n - 1) + 1;
+        }
+        print count(900);
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(run.lines(), vec!["900"]);
+}
+
+#[test]
+fn test_step_budget() {
+    let mut lox = Lox::default().stdout(Buffer::default()).max_steps(1000);
+    let prog = r#"
+        fun spin() {
+            while (true) {}
+        }
+        try {
+            spin();
+        } catch (e) {
+            print "caught";
+        }
+    "#;
+    let err = lox.run(prog).unwrap_err();
+    assert!(
+        matches!(err, LoxError::Limit(LimitError::Steps(1000))),
+        "{err:?}"
+    );
+    assert_eq!(err.to_string(), "execution budget of 1000 steps exceeded");
+
+    // the budget applies to each run, and the interpreter is still usable after exceeding it.
+    lox.run("var i = 0; while (i < 500) i = i + 1;").unwrap();
+    lox.run("print spin;").unwrap();
+}
+
+#[test]
+fn test_timeout() {
+    let mut lox = Lox::default().timeout(Duration::from_millis(20));
+    let err = lox.run("while (true) {}").unwrap_err();
+    assert!(
+        matches!(err, LoxError::Limit(LimitError::Timeout(_))),
+        "{err:?}"
+    );
+    lox.run("var x = 1;").unwrap();
+
+    // a sleep is cut short at the deadline, and the script times out when it carries on.
+    let start = std::time::Instant::now();
+    let err = lox
+        .run("time.sleep(time.hours(1)); while (true) {}")
+        .unwrap_err();
+    assert!(
+        matches!(err, LoxError::Limit(LimitError::Timeout(_))),
+        "{err:?}"
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_memory_limit() {
+    let stdout = Buffer::default();
+    let mut lox = Lox::default().stdout(stdout.clone()).max_memory(1 << 20);
+    let err = lox
+        .run(r#"var s = "x"; while (true) s = s + s;"#)
+        .unwrap_err();
+    assert!(
+        matches!(err, LoxError::OutOfMemory { limit: 1048576 }),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "out of memory: limit of 1048576 bytes exceeded"
+    );
+
+    // the limit can't be caught.
+    let prog = r#"
+        fun grow() {
+            var s = "x";
+            while (true) s = s + s;
+        }
+        try {
+            grow();
+        } catch (e) {
+            print "caught";
+        }
+    "#;
+    let err = lox.run(prog).unwrap_err();
+    assert!(
+        matches!(err, LoxError::OutOfMemory { limit: 1048576 }),
+        "{err:?}"
+    );
+
+    // memory that is no longer in use is credited back, so allocating far more than the limit
+    // in total is fine as long as little of it is live at once.
+    let prog = r#"
+        s = nil;
+        class Bag {}
+        var bag = Bag();
+        var i = 0;
+        while (i < 2000) {
+            bag.x = i;
+            var chunk = "x";
+            var j = 0;
+            while (j < 10) {
+                chunk = chunk + chunk;
+                j = j + 1;
+            }
+            i = i + 1;
+        }
+        print bag.x;
+    "#;
+    lox.run(prog).unwrap();
+    assert_eq!(stdout.to_string(), "1999\n");
+
+    // while memory that is still in use counts against the limit across runs.
+    lox.run(r#"s = "x"; var k = 0; while (k < 19) { s = s + s; k = k + 1; }"#)
+        .unwrap();
+    let err = lox.run(r#"var t = s + "x";"#).unwrap_err();
+    assert!(
+        matches!(err, LoxError::OutOfMemory { limit: 1048576 }),
+        "{err:?}"
+    );
+    lox.run(r#"s = nil; print "a" + "b";"#).unwrap();
+    assert_eq!(stdout.to_string(), "1999\nab\n");
+}
+
+#[test]
+fn test_register_fn() {
+    let stdout = Buffer::default();
+    let mut lox = Lox::default()
+        .stdout(stdout.clone())
+        .register_fn("hypot", 2, |_, args| {
+            let x = args[0].as_number()?;
+            let y = f64::try_from(args[1].clone())?;
+            Ok(Value::Number((x * x + y * y).sqrt()))
+        })
+        .register_fn("shout", 2, |_, args| {
+            let s = args[0].as_str()?.to_uppercase();
+            let excited = args[1].as_bool()?;
+            Ok(Value::String(if excited { s + "!" } else { s }))
+        });
+    lox.run(r#"print hypot(3, 4); print shout("hi", true);"#)
+        .unwrap();
+    assert_eq!(stdout.to_string(), "5\nHI!\n");
+
+    let err = lox.run(r#"hypot(3, "4");"#).unwrap_err();
+    assert!(
+        err.to_string().ends_with("expected number but got string"),
+        "{err}"
+    );
+    let err = lox.run(r#"try { shout(nil, 1); } catch (e) { throw e.message; }"#);
+    assert!(err
+        .unwrap_err()
+        .to_string()
+        .ends_with("expected string but got nil"),);
+}
+
+#[test]
+fn test_variadic_native() {
+    let stdout = Buffer::default();
+    let mut lox = Lox::default()
+        .stdout(stdout.clone())
+        .register_fn("sum", Arity::at_least(1), |_, args| {
+            let nums = args.iter().map(Value::as_number);
+            Ok(Value::Number(nums.sum::<Result<f64, _>>()?))
+        })
+        .register_fn("greet", Arity::range(1, 2), |_, args| {
+            let name = args[0].as_str()?;
+            let greeting = args.get(1).map(Value::as_str).transpose()?;
+            Ok(format!("{}, {name}", greeting.unwrap_or("hello")).into())
+        });
+    lox.run(r#"print sum(1); print sum(1, 2, 3); print greet("bob"); print greet("bob", "hi");"#)
+        .unwrap();
+    assert_eq!(stdout.to_string(), "1\n6\nhello, bob\nhi, bob\n");
+
+    let err = lox.run("sum();").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: expected at least 1 args but got 0"
+    );
+    let err = lox.run(r#"greet("a", "b", "c");"#).unwrap_err();
+    assert_eq!(err.to_string(), "line 1: expected 1 to 2 args but got 3");
+}
+
+#[test]
+fn test_default_and_rest_params() {
+    let prog = r#"
+        var base = 10;
+        fun f(a, b = a + base, ...rest) {
+            print a;
+            print b;
+            print rest;
+        }
+        f(1);
+        base = 20;
+        f(1);
+        f(1, 2, 3, "four");
+        fun g(...all) {
+            print all;
+        }
+        g();
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "1",
+            "11",
+            "[]",
+            "1",
+            "21",
+            "[]",
+            "1",
+            "2",
+            r#"[3, "four"]"#,
+            "[]"
+        ]
+    );
+}
+
+#[test]
+fn test_default_params_arity() {
+    let prog = r#"
+        fun f(a, b = 2) {}
+        f(1, 2, 3);
+    "#;
+    let err = run_prog(prog).unwrap_err();
+    assert_eq!(err.to_string(), "line 2: expected 1 to 2 args but got 3");
+    let err = run_prog("fun f(a, ...rest) {} f();").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: expected at least 1 args but got 0"
+    );
+}
+
+struct Point {
+    x: Cell<f64>,
+    y: Cell<f64>,
+}
+
+impl NativeObject for Point {
+    fn class_name(&self) -> &str {
+        "Point"
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match name {
+            "x" => Some(self.x.get().into()),
+            "y" => Some(self.y.get().into()),
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Value) -> Result<bool, ValueError> {
+        match name {
+            "x" => self.x.set(value.as_number()?),
+            "y" => self.y.set(value.as_number()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn method_arity(&self, name: &str) -> Option<Arity> {
+        match name {
+            "len" => Some(Arity::exact(0)),
+            "scale" => Some(Arity::exact(1)),
+            "visit" => Some(Arity::exact(1)),
+            _ => None,
+        }
+    }
+
+    fn call_method(
+        &self,
+        int: &mut Interpreter,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, CallableError> {
+        match name {
+            "len" => Ok(self.x.get().hypot(self.y.get()).into()),
+            "scale" => {
+                let k = args[0].as_number()?;
+                self.x.set(self.x.get() * k);
+                self.y.set(self.y.get() * k);
+                Ok(Value::Nil)
+            }
+            // calls back into the script while the point is borrowed
+            "visit" => int.call_value(&args[0], vec![]),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_native_class() {
+    let stdout = Buffer::default();
+    let mut lox = Lox::default()
+        .stdout(stdout.clone())
+        .register_class("Point", 2, |_, args| {
+            Ok(Point {
+                x: Cell::new(args[0].as_number()?),
+                y: Cell::new(args[1].as_number()?),
+            })
+        })
+        .register_fn("sum", 1, |_, args| {
+            let Value::Native(p) = &args[0] else {
+                return Err(CallableError::Generic("expected a point".into()));
+            };
+            let sum = p.with(|p: &Point| p.x.get() + p.y.get());
+            Ok(sum.map(Value::Number).unwrap_or(Value::Nil))
+        });
+    let prog = r#"
+        var p = Point(3, 4);
+        print p;
+        print p.x;
+        print p.len();
+        p.y = 0;
+        var scale = p.scale;
+        scale(2);
+        print p.x;
+        print sum(p);
+        print type(Point);
+        print instanceof(p, Point);
+        print instanceof(p, Error);
+        print arity(Point);
+        print Point;
+    "#;
+    lox.run(prog).unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "Point instance\n3\n5\n6\n6\nclass\ntrue\nfalse\n2\nPoint\n"
+    );
+
+    let err = lox.run("p.z;").unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined property 'z'");
+    let err = lox.run(r#"p.x = "one";"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: invalid value for property 'x': expected number but got string"
+    );
+
+    // the point can be read from inside its own method, but not set
+    let prog = r#"
+        fun show() { print p; }
+        fun get() { print p.x + p.len(); }
+        fun set() { p.x = 1; }
+        p.visit(show);
+        p.visit(get);
+        try {
+            p.visit(set);
+        } catch (e) {
+            print e.message;
+        }
+        p.visit(set);
+    "#;
+    stdout.clone().take();
+    let err = lox.run(prog).unwrap_err();
+    assert_eq!(
+        stdout.to_string(),
+        "Point instance\n12\nline 4: 'x' accessed while its object is in use\n"
+    );
+    assert!(
+        err.to_string()
+            .ends_with("'x' accessed while its object is in use"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_call_from_host() {
+    let stdout = Buffer::default();
+    let mut lox = Lox::default().stdout(stdout.clone());
+    let prog = r#"
+        var count = 0;
+        fun on_event(payload) {
+            count = count + 1;
+            print "event " + payload;
+            return count;
+        }
+        fun fail(n) {
+            return n / 0;
+        }
+        var not_fn = 1;
+    "#;
+    lox.run(prog).unwrap();
+    assert!(lox.get_global("on_event").unwrap().as_callable().is_some());
+    assert_eq!(lox.call("on_event", vec!["a".into()]).unwrap(), 1.0.into());
+    assert_eq!(lox.call("on_event", vec!["b".into()]).unwrap(), 2.0.into());
+    assert_eq!(stdout.to_string(), "event a\nevent b\n");
+    assert_eq!(lox.get_global("count"), Some(2.0.into()));
+
+    let err = lox.call("missing", vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "undefined global 'missing'");
+    let err = lox.call("not_fn", vec![]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "global 'not_fn' is not a function or class"
+    );
+    let err = lox.call("on_event", vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "expected 1 args but got 0");
+    let err = lox.call("fail", vec![1.0.into()]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        [
+            "Traceback (most recent call last):",
+            "  in <host>",
+            "  line 9, in fail",
+            "divide by zero detected at line 9",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn test_math() {
+    let prog = r#"
+        print math.sqrt(16);
+        print math.pow(2, 10);
+        print math.abs(-1.5);
+        print math.floor(-1.5);
+        print math.ceil(1.2);
+        print math.round(2.5);
+        print math.sin(0) + math.cos(0) + math.tan(0);
+        print math.log(math.exp(2));
+        print math.min(3, 1, 2);
+        print math.max(3, 1, 2);
+        print math.max(1, math.nan) == math.max(1, math.nan);
+        print math.is_nan(math.min(math.nan, 1));
+        print math.is_nan(math.sqrt(-1));
+        print -math.inf < math.pi and math.pi < math.e * 2;
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "4", "1024", "1.5", "-2", "2", "3", "1", "2", "1", "3", "false", "true", "true", "true"
+        ]
+    );
+
+    let mut lox = Lox::default();
+    let err = lox.run(r#"math.sqrt("4");"#).unwrap_err();
+    assert!(
+        err.to_string().ends_with("expected number but got string"),
+        "{err}"
+    );
+    let err = lox.run("math.max();").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: expected at least 1 args but got 0"
+    );
+}
+
+#[test]
+fn test_random() {
+    assert_eq!(Rng::new(0).next_u64(), 0xe220a8397b1dcdaf);
+
+    let prog = r#"
+        print random.random();
+        print random.random_int(1, 6);
+        print random.random_int(-3, -3);
+        var xs = json_parse("[1, 2, 3, 4, 5]");
+        random.shuffle(xs);
+        print xs;
+        print random.choice(xs);
+        random.seed(7);
+        var first = random.random();
+        random.seed(7);
+        print first == random.random();
+    "#;
+    let stdout = Buffer::default();
+    Lox::default()
+        .stdout(stdout.clone())
+        .seed(42)
+        .run(prog)
+        .unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "0.7415648787718233\n2\n-3\n[4, 2, 1, 3, 5]\n3\ntrue\n"
+    );
+
+    let mut lox = Lox::default();
+    for (src, ex) in [
+        ("random.random_int(2, 1);", "empty range 2 to 1"),
+        (
+            "random.random_int(1.5, 2);",
+            "expected an integer but got 1.5",
+        ),
+        (
+            r#"random.choice(json_parse("[]"));"#,
+            "cannot choose from an empty list",
+        ),
+        ("random.shuffle(1);", "expected list but got number"),
+    ] {
+        let err = lox.run(src).unwrap_err();
+        assert!(err.to_string().ends_with(ex), "{err}");
+    }
+}
+
+#[test]
+fn test_read_stdin() {
+    let prog = r#"
+        var name = input("name: ");
+        print "hi " + name;
+        var line = read_line();
+        while (line != nil) {
+            print "> " + line;
+            line = read_line();
+        }
+        print input("more? ");
+    "#;
+    let stdout = Buffer::default();
+    Lox::default()
+        .stdin(io::Cursor::new("lox\nfirst\r\n\nlast"))
+        .stdout(stdout.clone())
+        .run(prog)
+        .unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "name: hi lox\n> first\n> \n> last\nmore? nil\n"
+    );
+}
+
+#[test]
+fn test_process_natives() {
+    let prog = r#"
+        print args;
+        print getenv("ROX_VAR");
+        print getenv("ROX_UNSET_VAR");
+        try {
+            try {
+                exit(3);
+            } catch (e) {
+                print "caught";
+            }
+        } finally {
+            print "finally";
+        }
+        print "unreachable";
+    "#;
+    let stdout = Buffer::default();
+    let err = Lox::default()
+        .stdout(stdout.clone())
+        .args(["a", "--b"])
+        .env_vars([("ROX_VAR", "rox")])
+        .run(prog)
+        .unwrap_err();
+    assert!(matches!(err, LoxError::Exit(3)), "{err}");
+    assert_eq!(stdout.to_string(), "[\"a\", \"--b\"]\nrox\nnil\nfinally\n");
+
+    // the host's environment is hidden unless it is passed in
+    let stdout = Buffer::default();
+    Lox::default()
+        .stdout(stdout.clone())
+        .run(r#"print getenv("CARGO_PKG_NAME");"#)
+        .unwrap();
+    assert_eq!(stdout.to_string(), "nil\n");
+
+    let mut lox = Lox::default();
+    assert!(matches!(lox.run("exit();"), Err(LoxError::Exit(0))));
+    let err = lox.run("exit(256);").unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("exit code must be an integer from 0 to 255 but got 256"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_formatted_output() {
+    let prog = r#"
+        write("a");
+        write(1);
+        print "";
+        eprint("oops");
+        print format("{} has {:.2}", "pi", math.pi);
+        print format("[{:5}|{:<5}|{:^5}|{:>5}]", 1, 2, "ab", "c");
+        print format("{1}{0}{} {{}} {2:.3}", "x", "y", "truncated");
+    "#;
+    let (stdout, stderr) = (Buffer::default(), Buffer::default());
+    Lox::default()
+        .stdout(stdout.clone())
+        .stderr(stderr.clone())
+        .run(prog)
+        .unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "a1\npi has 3.14\n[    1|2    | ab  |    c]\nyxx {} tru\n"
+    );
+    assert_eq!(stderr.to_string(), "oops\n");
+
+    let mut lox = Lox::default();
+    for (src, ex) in [
+        (r#"format("{");"#, "unmatched '{'"),
+        (r#"format("}");"#, "unmatched '}'"),
+        (r#"format("{} {}", 1);"#, "no arg at index 1 of 1 args"),
+        (r#"format("{:x}", 1);"#, "invalid spec 'x'"),
+        (r#"format("{a}", 1);"#, "invalid index 'a'"),
+        (
+            r#"format("{:2000000000}", 1);"#,
+            "width and precision of '2000000000' must be at most 1024",
+        ),
+        (
+            r#"format("{:.1025}", 1);"#,
+            "width and precision of '.1025' must be at most 1024",
+        ),
+    ] {
+        let err = lox.run(src).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with(&format!("invalid format string: {ex}")),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_format_memory_limit() {
+    // every placeholder is padded to the widest allowed width, which the memory limit stops as the
+    // output grows rather than once it has been built.
+    let prog = r#"
+        var template = "{0:1024}";
+        var i = 0;
+        while (i < 12) {
+            template = template + template;
+            i = i + 1;
+        }
+        format(template, 1);
+    "#;
+    let err = Lox::default().max_memory(1 << 20).run(prog).unwrap_err();
+    assert!(matches!(err, LoxError::OutOfMemory { .. }), "{err:?}");
+}
+
+#[test]
+fn test_time() {
+    let prog = r#"
+        var start = time.now_ms();
+        time.sleep(time.seconds(1.5));
+        print time.now_ms() - start;
+        print time.format_time(time.now(), "%Y-%m-%d %H:%M:%S.%L");
+        var d = time.date(2024, 2, 28, 23, 30);
+        print time.format_time(d + time.hours(1), "%d/%m/%Y %H:%M %%");
+        print time.format_time(time.date(1969, 12, 31), "%Y-%m-%d");
+        print time.parse_time("2000-03-01", "%Y-%m-%d") - time.date(2000, 2, 28) == time.days(2);
+        print clock();
+    "#;
+    let clock = ManualClock::new(Duration::from_millis(1_700_000_000_123));
+    let stdout = Buffer::default();
+    let mut lox = Lox::default().stdout(stdout.clone()).clock(clock.clone());
+    lox.run(prog).unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "1500\n2023-11-14 22:13:21.623\n29/02/2024 00:30 %\n1969-12-31\ntrue\n1700000001.623\n"
+    );
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(
+        lox.call("clock", vec![]).unwrap(),
+        Value::Number(1_700_000_061.623)
+    );
+
+    for (src, ex) in [
+        (
+            "time.date(2023, 2, 29);",
+            "invalid date 2023-02-29 00:00:00.000",
+        ),
+        (
+            r#"time.parse_time("2024-1-x", "%Y-%m-%d");"#,
+            "time '2024-1-x' does not match format '%Y-%m-%d'",
+        ),
+        (
+            r#"time.format_time(0, "%Q");"#,
+            "invalid time format specifier '%Q'",
+        ),
+        (
+            "time.sleep(-1);",
+            "expected a duration of 0 or more ms but got -1",
+        ),
+        (
+            "time.date(2000000000, 1, 1);",
+            "invalid date 2000000000-01-01 00:00:00.000",
+        ),
+        (
+            "time.date(275761, 1, 1);",
+            "invalid date 275761-01-01 00:00:00.000",
+        ),
+        (
+            "time.sleep(time.days(1000000000000000000000));",
+            "duration of 86400000000000000000000000000 ms is too long",
+        ),
+    ] {
+        let err = lox.run(src).unwrap_err();
+        assert!(err.to_string().ends_with(ex), "{err}");
+    }
+}
+
+#[test]
+fn test_introspection() {
+    let prog = r#"
+        class Point {}
+        class Other {}
+        var p = Point();
+        p.y = 2;
+        p.x = 1;
+        fun f(a, b = 1, ...rest) {}
+        fun g(a, b) {}
+        print type(1) + " " + type("s") + " " + type(true) + " " + type(nil);
+        print type(f) + " " + type(clock) + " " + type(Point) + " " + type(p);
+        print type(json_parse("[]")) + " " + type(json_parse("{}")) + " " + type(math);
+        print instanceof(p, Point);
+        print instanceof(p, Other);
+        print instanceof(1, Point);
+        print fields(p);
+        print fields(Other());
+        print arity(g);
+        print arity(Point);
+        print arity(f);
+        print arity(format);
+        print arity(json_stringify);
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "number string bool nil",
+            "function function class instance",
+            "list map module",
+            "true",
+            "false",
+            "false",
+            r#"["x", "y"]"#,
+            "[]",
+            "2",
+            "0",
+            r#"{"max": nil, "min": 1}"#,
+            r#"{"max": nil, "min": 1}"#,
+            r#"{"max": 2, "min": 1}"#,
+        ]
+    );
+
+    let mut lox = Lox::default();
+    for (src, ex) in [
+        ("instanceof(1, 2);", "expected class but got number"),
+        ("fields(1);", "expected instance but got number"),
+        ("arity(1);", "expected function but got number"),
+    ] {
+        let err = lox.run(src).unwrap_err();
+        assert!(err.to_string().ends_with(ex), "{err}");
+    }
+}
+
+#[test]
+fn test_identity() {
+    let prog = r#"
+        class Box {}
+        var a = Box();
+        var b = Box();
+        a.v = 1;
+        b.v = 1;
+        var c = a;
+        print a == b;
+        print a == c;
+        print a != b;
+        fun make() {
+            fun f() {}
+            return f;
+        }
+        var f = make();
+        print f == make();
+        print f == f;
+        print clock == clock;
+
+        // a method bound to the same instance is the same function each time it's accessed
+        class Counter {
+            get() { return this.n; }
+        }
+        var x = Counter();
+        var y = Counter();
+        x.n = 1;
+        y.n = 2;
+        var get = x.get;
+        print x.get == x.get;
+        print get == x.get;
+        print x.get == y.get;
+        print get() + y.get();
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec!["false", "true", "true", "false", "true", "true", "true", "true", "false", "3"]
+    );
+}
+
+#[test]
+fn test_equals_and_hash() {
+    let prog = r#"
+        class Point {
+            equals(other) {
+                return instanceof(other, Point) and this.x == other.x and this.y == other.y;
+            }
+            hash() {
+                return this.x * 31 + this.y;
+            }
+        }
+        fun point(x, y) {
+            var p = Point();
+            p.x = x;
+            p.y = y;
+            return p;
+        }
+        print point(1, 2) == point(1, 2);
+        print point(1, 2) != point(2, 1);
+        print point(1, 2) == 1;
+        print 1 == point(1, 2);
+
+        var m = map();
+        map_set(m, point(1, 2), "a");
+        map_set(m, point(1, 2), "b");
+        map_set(m, 1, "one");
+        map_set(m, "k", "v");
+        print map_get(m, point(1, 2));
+        print map_has(m, point(2, 1));
+        print map_get(m, 1);
+        print map_get(m, "k");
+        print m;
+
+        class Key {}
+        var k = Key();
+        map_set(m, k, "key");
+        print map_get(m, k);
+        print map_has(m, Key());
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "true",
+            "true",
+            "false",
+            "false",
+            "b",
+            "false",
+            "one",
+            "v",
+            r#"{"k": "v", Point instance: "b", 1: "one"}"#,
+            "key",
+            "false",
+        ]
+    );
+
+    // lists are equal if their elements are, including by equals methods
+    let prog = r#"
+        class Any {
+            equals(other) { return true; }
+        }
+        fun list(...items) { return items; }
+        var a = list(1, list(2, 3));
+        print a == list(1, list(2, 3));
+        print a == list(1, list(2, 0));
+        print a == list(1);
+        print list(1, Any()) == list(1, 2);
+        print list(Any(), 1) == list(2, 2);
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(run.lines(), vec!["true", "false", "false", "true", "false"]);
+
+    // lists that contain themselves can be compared
+    let (a, b) = (List::default(), List::default());
+    a.push(Value::List(a.clone()));
+    b.push(Value::List(b.clone()));
+    let mut int = Interpreter::default();
+    assert!(int
+        .values_equal(&Value::List(a), &Value::List(b), None)
+        .unwrap());
+
+    // functions and classes are keyed by identity
+    let prog = r#"
+        fun f() {}
+        fun make() {
+            fun g() {}
+            return g;
+        }
+        class C {}
+        var m = map();
+        map_set(m, f, "f");
+        map_set(m, C, "C");
+        map_set(m, clock, "clock");
+        map_set(m, make(), "g");
+        print map_get(m, f);
+        print map_get(m, C);
+        print map_get(m, clock);
+        print map_has(m, make());
+        print m;
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "f",
+            "C",
+            "clock",
+            "false",
+            r#"{<fn f>: "f", C: "C", <native fn clock>: "clock", <fn g>: "g"}"#
+        ]
+    );
+
+    let err = run_prog(r#"map_set(map(), json_parse("[]"), 1);"#).unwrap_err();
+    assert!(err.to_string().contains("list can't be used as a map key"));
+    let err = run_prog("map_set(map(), map(), 1);").unwrap_err();
+    assert!(err.to_string().contains("map can't be used as a map key"));
+
+    // errors from keys are reported on the line of the native that used them
+    let prog = r#"
+        class BadHash {
+            hash() { return "x"; }
+        }
+        class BadEquals {
+            hash() { return 1; }
+            equals(other) { return nil.x; }
+        }
+        var m = map();
+        map_set(m, BadEquals(), 1);
+        try {
+            map_get(m, map());
+        } catch (e) {
+            print format("{}: {}", e.line, e.message);
+        }
+        try {
+            map_has(m, BadHash());
+        } catch (e) {
+            print format("{}: {}", e.line, e.message);
+        }
+        try {
+            map_set(m, BadEquals(), 2);
+        } catch (e) {
+            print format("{}: {}", e.line, e.message);
+        }
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "11: map can't be used as a map key",
+            "16: hash must return a number but got string",
+            "6: line 6: only instances have properties",
+        ]
+    );
+
+    // maps are compared by identity, so one that contains itself can be compared and printed.
+    let prog = r#"
+        var m = map();
+        map_set(m, "self", m);
+        map_set(m, "list", json_parse("[1]"));
+        print m == m;
+        print map() == map();
+        print map_get(m, "self") == m;
+        print m;
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec!["true", "false", "true", r#"{"list": [1], "self": {...}}"#]
+    );
+    let err = run_prog(
+        r#"
+        var m = map();
+        map_set(m, 1, 1);
+        json_stringify(m);
+    "#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("non-string keys"));
+}
+
+#[test]
+fn test_this() {
+    let prog = r#"
+        class Counter {
+            incr() {
+                this.n = this.n + 1;
+                return this;
+            }
+        }
+        var c = Counter();
+        c.n = 0;
+        c.incr().incr();
+        var incr = c.incr;
+        incr();
+        print c.n;
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(run.lines(), vec!["3"]);
+
+    let err = run_prog("print this;").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("can't use 'this' outside of a method"));
+}
+
+#[test]
+fn test_operator_overloading() {
+    let prog = r#"
+        class Vec {
+            __add(other) { return vec(this.x + other.x, this.y + other.y); }
+            __sub(other) { return vec(this.x - other.x, this.y - other.y); }
+            __mul(k) { return vec(this.x * k, this.y * k); }
+            __rmul(k) { return this * k; }
+            __rsub(k) { return vec(k - this.x, k - this.y); }
+            __neg() { return vec(-this.x, -this.y); }
+            __lt(other) { return this.len() < other.len(); }
+            __gt(other) { return this.len() > other.len(); }
+            __eq(other) { return instanceof(other, Vec) and this.x == other.x and this.y == other.y; }
+            __str() { return format("({}, {})", this.x, this.y); }
+            len() { return this.x * this.x + this.y * this.y; }
+        }
+        fun vec(x, y) {
+            var v = Vec();
+            v.x = x;
+            v.y = y;
+            return v;
+        }
+        var a = vec(1, 2);
+        var b = vec(3, 4);
+        print a + b;
+        print b - a;
+        print a * 3;
+        print 3 * a;
+        print 10 - a;
+        print -a;
+        print a < b;
+        print b > a;
+        print a == vec(1, 2);
+        print a != b;
+        print format("{} and {}", a, b);
+        write(a);
+        print "";
+        print json_parse("[]");
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(
+        run.lines(),
+        vec![
+            "(4, 6)",
+            "(2, 2)",
+            "(3, 6)",
+            "(3, 6)",
+            "(9, 8)",
+            "(-1, -2)",
+            "true",
+            "true",
+            "true",
+            "true",
+            "(1, 2) and (3, 4)",
+            "(1, 2)",
+            "[]",
+        ]
+    );
+
+    // operators that a class doesn't overload still require numbers
+    let err = run_prog("class A {} print A() * 2;").unwrap_err();
+    assert!(err.to_string().contains("expected numbers for op"));
+    let err = run_prog("class A { __mul(k) { return 1; } } print 2 * A();").unwrap_err();
+    assert!(err.to_string().contains("expected numbers for op"));
+
+    // the reflected comparison is the mirror image of the operator
+    let prog = r#"
+        class Size {
+            __lt(n) { return "lt"; }
+            __gt(n) { return "gt"; }
+            __le(n) { return "le"; }
+            __ge(n) { return "ge"; }
+        }
+        var s = Size();
+        print 3 < s;
+        print 3 > s;
+        print 3 <= s;
+        print 3 >= s;
+        print s < s;
+    "#;
+    let run = run_prog(prog).unwrap();
+    assert_eq!(run.lines(), vec!["gt", "lt", "ge", "le", "lt"]);
+    let err = run_prog("class A { __str() { return 1; } } print A();").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("__str must return a string but got number"));
+
+    // errors in __str are traced back to the print or the native that converted the value
+    for (convert, line) in [("print a;", 5), (r#"format("{}", a);"#, 5)] {
+        let prog = format!(
+            r#"
+            class A {{
+                __str() {{ return 1 / 0; }}
+            }}
+            var a = A();
+            {convert}
+            "#
+        );
+        let err = run_prog(&prog).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("  line {line}, in <script>")),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_expression_output() {
+    // a line that is a single expression prints its value the same as print, to the configured
+    // stdout
+    let stdout = Buffer::default();
+    let mut lox = Lox::default().stdout(stdout.clone());
+    lox.run("class P { __str() { return \"p\"; } } var p = P();")
+        .unwrap();
+    lox.run("p").unwrap();
+    lox.run("1 + 2").unwrap();
+    lox.run(r#""a" + "b""#).unwrap();
+    assert_eq!(stdout.to_string(), "p\n3\nab\n");
+
+    // strings are printed without quotes, as print shows them
+    stdout.clone().take();
+    lox.run(r#""hello""#).unwrap();
+    lox.run(r#"json_parse("[1]")"#).unwrap();
+    assert_eq!(stdout.to_string(), "hello\n[1]\n");
+
+    lox.run("class Q { __str() { return 1 / 0; } } var q = Q();")
+        .unwrap();
+    let err = lox.run("q").unwrap_err();
+    assert!(err.to_string().contains("in __str"), "{err}");
+
+    // a __str that returns something else is reported on the line that printed the value
+    let prog = r#"
+        class R { __str() { return 1; } }
+        try {
+            print R();
+        } catch (e) {
+            print format("{}: {}", e.line, e.message);
+        }
+    "#;
+    stdout.clone().take();
+    lox.run(prog).unwrap();
+    assert_eq!(
+        stdout.to_string(),
+        "4: __str must return a string but got number\n"
+    );
+}
+
 #[derive(Debug)]
 struct Run {
     stdout: Vec<u8>,
@@ -151,7 +1463,7 @@ fn run_prog(prog: impl AsRef<str>) -> Result<Run, Box<dyn std::error::Error>> {
 }
 
 #[derive(Clone, Default)]
-struct Buffer {
+pub(super) struct Buffer {
     bs: Rc<RefCell<Vec<u8>>>,
 }
 
@@ -0,0 +1,168 @@
+use super::interpreter::Buffer;
+use crate::prelude::*;
+
+#[test]
+fn test_parse() {
+    let value =
+        json::parse(r#" {"b": [1, 2.5, -3e2], "a": "x\ny é😀", "c": {"d": null, "e": true}} "#)
+            .unwrap();
+    assert_eq!(
+        value.to_string(),
+        r#"{"a": "x
+y é😀", "b": [1, 2.5, -300], "c": {"d": nil, "e": true}}"#
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    for (src, ex) in [
+        (
+            "",
+            "invalid json at line 1, column 1: unexpected end of input",
+        ),
+        (
+            "[1, 2",
+            "invalid json at line 1, column 6: expected ',' but got end of input",
+        ),
+        (
+            "{\n  \"a\": tru\n}",
+            "invalid json at line 2, column 8: invalid literal",
+        ),
+        (
+            "[01]",
+            "invalid json at line 1, column 3: expected ',' but got '1'",
+        ),
+        (
+            r#"{"a" 1}"#,
+            "invalid json at line 1, column 6: expected ':' but got '1'",
+        ),
+        (
+            "[1] x",
+            "invalid json at line 1, column 5: unexpected trailing characters",
+        ),
+        (
+            r#""\u+041""#,
+            "invalid json at line 1, column 4: invalid unicode escape",
+        ),
+    ] {
+        let err = json::parse(src).unwrap_err();
+        assert_eq!(err.to_string(), ex, "{src}");
+    }
+}
+
+#[test]
+fn test_stringify() {
+    let value = json::parse(r#"{"b": [1, {"c": "q\"uote"}, []], "a": {}}"#).unwrap();
+    assert_eq!(
+        json::stringify(&value, None).unwrap(),
+        r#"{"a":{},"b":[1,{"c":"q\"uote"},[]]}"#
+    );
+    assert_eq!(
+        json::stringify(&value, Some(2)).unwrap(),
+        r#"{
+  "a": {},
+  "b": [
+    1,
+    {
+      "c": "q\"uote"
+    },
+    []
+  ]
+}"#
+    );
+}
+
+#[test]
+fn test_json_natives() {
+    let stdout = Buffer::default();
+    let prog = r#"
+        class Point {}
+        var p = Point();
+        p.x = 1;
+        p.y = json_parse("[true, null]");
+        print json_stringify(p);
+        fun pretty(src) {
+            return json_stringify(json_parse(src), 1);
+        }
+        fun try_parse(src) {
+            try {
+                return json_parse(src);
+            } catch (e) {
+                return e.message;
+            }
+        }
+    "#;
+    let mut lox = Lox::default().stdout(stdout.clone());
+    lox.run(prog).unwrap();
+    assert_eq!(stdout.to_string(), "{\"x\":1,\"y\":[true,null]}\n");
+    let pretty = lox.call("pretty", vec![r#"{"k": 1.5}"#.into()]).unwrap();
+    assert_eq!(pretty, "{\n \"k\": 1.5\n}".into());
+    let err = lox.call("try_parse", vec![r#"{"k": }"#.into()]).unwrap();
+    assert_eq!(
+        err,
+        "invalid json at line 1, column 7: unexpected character '}'".into()
+    );
+    let err = lox.run("json_stringify(clock);").unwrap_err();
+    assert!(
+        err.to_string().ends_with("cannot encode function as json"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_json_indent() {
+    let mut lox = Lox::default().stdout(Buffer::default());
+    for indent in ["1000000000000000", "-1", "1.5", "11"] {
+        let err = lox
+            .run(format!(r#"json_stringify(json_parse("[1]"), {indent});"#))
+            .unwrap_err();
+        assert!(
+            err.to_string().ends_with(&format!(
+                "indent must be an integer from 0 to 10 but got {indent}"
+            )),
+            "{err}"
+        );
+    }
+    lox.run(r#"json_stringify(json_parse("[1]"), 10);"#)
+        .unwrap();
+    assert_eq!(
+        json::stringify(&Value::List(vec![1.0.into()].into()), Some(usize::MAX)).unwrap(),
+        "[\n          1\n]"
+    );
+}
+
+#[test]
+fn test_json_memory_limit() {
+    // each level holds the one below twice, so the json doubles in length with each level
+    // while the maps themselves stay small.
+    let prog = r#"
+        var m = map();
+        var i = 0;
+        while (i < 40) {
+            var next = map();
+            map_set(next, "a", m);
+            map_set(next, "b", m);
+            m = next;
+            i = i + 1;
+        }
+        json_stringify(m);
+    "#;
+    let mut lox = Lox::default().max_memory(1 << 20);
+    let err = lox.run(prog).unwrap_err();
+    assert!(matches!(err, LoxError::OutOfMemory { .. }), "{err:?}");
+
+    // the values parsed from json take far more memory than its source
+    let prog = r#"
+        var src = "[],";
+        var i = 0;
+        while (i < 12) {
+            src = src + src;
+            i = i + 1;
+        }
+        json_parse("[" + src + "[]]");
+    "#;
+    let mut lox = Lox::default().max_memory(1 << 17);
+    let err = lox.run(prog).unwrap_err();
+    assert!(matches!(err, LoxError::OutOfMemory { .. }), "{err:?}");
+    Lox::default().run(prog).unwrap();
+}
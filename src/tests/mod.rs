@@ -1,3 +1,45 @@
+#[cfg(feature = "serde")]
+mod convert;
+mod file;
 mod interpreter;
+mod json;
+mod module;
 mod parser;
 mod scanner;
+
+use crate::prelude::*;
+use std::ops::Deref;
+
+/// A fresh directory under the system temp dir for a test's files, which is removed along with
+/// its contents when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rox-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Writes a file at a path relative to the dir, creating its parent dirs.
+    pub(crate) fn write(&self, path: &str, contents: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,214 @@
+use super::{interpreter::Buffer, TempDir};
+use crate::prelude::*;
+
+// writes the files of a test project into a fresh temp dir, which is removed when dropped.
+fn project(name: &str, files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new(name);
+    for (path, src) in files {
+        dir.write(path, src.trim());
+    }
+    dir
+}
+
+#[test]
+fn test_import() {
+    let dir = project(
+        "import",
+        &[
+            (
+                "main.lox",
+                r#"
+import "lib/math.lox" as m;
+import "lib/math.lox" as again;
+print m.square(m.base);
+print again.base;
+print m;
+"#,
+            ),
+            (
+                "lib/math.lox",
+                r#"
+import "util.lox" as util;
+print "loading math";
+var base = util.three();
+fun square(n) { return n * n; }
+"#,
+            ),
+            ("lib/util.lox", "fun three() { return 3; }"),
+        ],
+    );
+    let stdout = Buffer::default();
+    Lox::new()
+        .stdout(stdout.clone())
+        .run_file(dir.join("main.lox"))
+        .unwrap();
+    assert_eq!(stdout.to_string(), "loading math\n9\n3\n<module m>\n");
+}
+
+#[test]
+fn test_import_errors() {
+    let dir = project(
+        "import-errors",
+        &[
+            ("a.lox", r#"import "b.lox" as b;"#),
+            ("b.lox", r#"import "a.lox" as a;"#),
+            ("missing.lox", r#"import "nope.lox" as nope;"#),
+            ("lib.lox", "var x = 1;"),
+            ("undefined.lox", r#"import "lib.lox" as lib; print lib.y;"#),
+        ],
+    );
+    let path = |name: &str| dir.join(name).canonicalize().unwrap();
+
+    let err = Lox::new().run_file(dir.join("a.lox")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "line 1: circular import: {} -> {} -> {}",
+            path("a.lox").display(),
+            path("b.lox").display(),
+            path("a.lox").display()
+        )
+    );
+
+    let err = Lox::new().run_file(dir.join("missing.lox")).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("line 1: could not import 'nope.lox': "));
+
+    let err = Lox::new().run_file(dir.join("undefined.lox")).unwrap_err();
+    assert_eq!(err.to_string(), "line 1: undefined property 'y'");
+}
+
+#[test]
+fn test_import_file_policy() {
+    let dir = project(
+        "import-policy",
+        &[
+            ("main.lox", r#"import "lib/ok.lox" as ok; print ok.x;"#),
+            ("lib/ok.lox", "var x = 1;"),
+            ("escape.lox", r#"import "secret.lox" as secret;"#),
+            ("secret.lox", "var x = 2;"),
+        ],
+    );
+    let policy = || FilePolicy::new().allow_read(dir.join("lib"));
+    let stdout = Buffer::default();
+    Lox::new()
+        .stdout(stdout.clone())
+        .file_policy(policy())
+        .run_file(dir.join("main.lox"))
+        .unwrap();
+    assert_eq!(stdout.to_string(), "1\n");
+
+    let err = Lox::new()
+        .file_policy(policy())
+        .run_file(dir.join("escape.lox"))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("line 1: could not import 'secret.lox': read access denied"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_memory_loader() {
+    let loader = MemoryLoader::from_iter([
+        (
+            "app/main.lox",
+            r#"
+import "lib/greet.lox" as greet;
+import "strings.lox" as strings;
+print greet.hello("lox");
+print strings.shout("hi");
+"#,
+        ),
+        (
+            "app/lib/greet.lox",
+            r#"
+import "../../vendor/strings.lox" as strings;
+fun hello(name) { return strings.shout("hello " + name); }
+"#,
+        ),
+        ("vendor/strings.lox", r#"fun shout(s) { return s + "!"; }"#),
+    ]);
+    let stdout = Buffer::default();
+    let mut lox = Lox::new()
+        .stdout(stdout.clone())
+        .loader(loader)
+        .search_path(["vendor"]);
+    lox.run_file("app/main.lox").unwrap();
+    assert_eq!(stdout.to_string(), "hello lox!\nhi!\n");
+
+    let err = lox.run(r#"import "missing.lox" as m;"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: could not import 'missing.lox': no module at app/missing.lox"
+    );
+    let err = lox.run_file("nope.lox").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not read nope.lox: no module at nope.lox"
+    );
+}
+
+#[test]
+fn test_import_traceback() {
+    let dir = project(
+        "import-traceback",
+        &[
+            ("main.lox", "import \"lib.lox\" as lib;\nlib.fail(0);"),
+            ("lib.lox", "fun fail(n) {\n    return 1 / n;\n}"),
+            ("top.lox", "print 1;\nimport \"broken.lox\" as broken;"),
+            ("broken.lox", "var x = 1;\nx.y;"),
+        ],
+    );
+    let path = |name: &str| dir.join(name).canonicalize().unwrap();
+
+    let err = Lox::new().run_file(dir.join("main.lox")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Traceback (most recent call last):\n  line 2, in <script>\n  {}, line 2, in fail\ndivide by zero detected at line 2",
+            path("lib.lox").display()
+        )
+    );
+
+    let err = Lox::new()
+        .stdout(Buffer::default())
+        .run_file(dir.join("top.lox"))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Traceback (most recent call last):\n  line 2, in <script>\n  {}, line 2, in <module>\nline 2: only instances have properties",
+            path("broken.lox").display()
+        )
+    );
+}
+
+#[test]
+fn test_as_is_not_reserved() {
+    let dir = project(
+        "as-name",
+        &[
+            (
+                "main.lox",
+                "var as = 1;\nimport \"lib.lox\" as as;\nprint as.two;",
+            ),
+            ("lib.lox", "var two = 2;"),
+        ],
+    );
+    let stdout = Buffer::default();
+    Lox::new()
+        .stdout(stdout.clone())
+        .run_file(dir.join("main.lox"))
+        .unwrap();
+    assert_eq!(stdout.to_string(), "2\n");
+
+    let err = Lox::new().run(r#"import "lib.lox" lib;"#).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("line 1: expected 'as' after import path"),
+        "{err}"
+    );
+}
@@ -5,7 +5,7 @@ use tracing_test::traced_test;
 #[test]
 fn assign() {
     let prog = "x=42;";
-    let scanner = Scanner::new(&prog);
+    let scanner = Scanner::new(prog);
     let tokens = scanner.scan_tokens().unwrap();
     let mut parser = Parser::new(tokens);
     let stmts = parser.parse().unwrap();
@@ -31,15 +31,22 @@ fn assign() {
 #[test]
 fn set() {
     let prog = "foo.x=42;";
-    let scanner = Scanner::new(&prog);
+    let scanner = Scanner::new(prog);
     let tokens = scanner.scan_tokens().unwrap();
     let mut parser = Parser::new(tokens);
     let stmts = parser.parse().unwrap();
-    // i know that this is wrong
     assert_eq!(
         stmts,
         vec![Stmt::Expr(ExprStmt {
-            expr: Expr::Assign(AssignExpr {
+            expr: Expr::Set(SetExpr {
+                object: Box::new(Expr::Var(VarExpr {
+                    name: Token {
+                        typ: TokenType::Identifier,
+                        lexeme: Lexeme::from("foo"),
+                        literal: None,
+                        line: 1
+                    },
+                })),
                 name: Token {
                     typ: TokenType::Identifier,
                     lexeme: Lexeme::from("x"),
@@ -53,3 +60,41 @@ fn set() {
         })]
     );
 }
+
+#[traced_test]
+#[test]
+fn multiple_errors() {
+    let prog = "var = 1;\nprint 2;\nprint (3;\nvar x = ;";
+    let scanner = Scanner::new(prog);
+    let tokens = scanner.scan_tokens().unwrap();
+    let mut parser = Parser::new(tokens);
+    let err = parser.parse().unwrap_err();
+    let lines = err.errors().iter().map(ToString::to_string).collect_vec();
+    assert_eq!(
+        lines,
+        vec![
+            "line 1: expected Identifier but was instead Equal",
+            "line 3: expected RightParen but was instead Semicolon",
+            "line 4: expected expression",
+        ]
+    );
+    assert_eq!(err.to_string(), lines.join("\n"));
+}
+
+#[traced_test]
+#[test]
+fn param_errors() {
+    let prog = "fun f(a = 1, b) {}\nfun g(...rest, a) {}";
+    let scanner = Scanner::new(prog);
+    let tokens = scanner.scan_tokens().unwrap();
+    let mut parser = Parser::new(tokens);
+    let err = parser.parse().unwrap_err();
+    let lines = err.errors().iter().map(ToString::to_string).collect_vec();
+    assert_eq!(
+        lines,
+        vec![
+            "line 1: param 'b' without a default follows a param with one",
+            "line 2: rest param 'rest' must be the last param",
+        ]
+    );
+}
@@ -0,0 +1,336 @@
+use crate::prelude::*;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// The furthest year from the epoch that a date may have, so that its time in ms is exactly
+/// representable as a lox number. This is the same range as javascript dates.
+pub const MAX_YEAR: i64 = 275_760;
+
+/// The source of the current time for scripts. Hosts can supply their own, such as a
+/// [`ManualClock`], to freeze time in tests.
+pub trait Clock {
+    /// The wall clock time since the unix epoch.
+    fn now(&self) -> Duration;
+
+    /// A time that never decreases, measured from an arbitrary starting point.
+    fn monotonic(&self) -> Duration;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock of the operating system. This is the default clock.
+#[derive(Clone, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves when it is advanced, either by the host or by a script sleeping.
+/// Clones share the same time, so a host can keep a clone to control the clock of a [`Lox`].
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+    elapsed: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    /// Creates a clock frozen at the specified time since the unix epoch.
+    pub fn new(now: Duration) -> Self {
+        let clock = Self::default();
+        clock.now.set(now);
+        clock
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Defines the built-in `time` module. Times are numbers of milliseconds since the unix epoch
+/// and durations are numbers of milliseconds, so they can be combined with arithmetic. Dates are
+/// always in UTC.
+pub(crate) fn define_module(int: &mut Interpreter) {
+    let bindings = [
+        NativeFunction::new("now", 0, |int, _| Ok(ms(int.clock().now()).into())),
+        NativeFunction::new("now_ms", 0, |int, _| Ok(ms(int.clock().monotonic()).into())),
+        // sleeps for at most the time left before the run times out, so that the timeout is
+        // raised when the script carries on
+        NativeFunction::new("sleep", 1, |int, args| {
+            let ms = args[0].as_number()?;
+            if ms.is_nan() || ms < 0.0 {
+                let msg = format!("expected a duration of 0 or more ms but got {ms}");
+                return Err(ValueError::Custom(msg).into());
+            }
+            let mut duration = Duration::try_from_secs_f64(ms / 1000.0)
+                .map_err(|_| ValueError::Custom(format!("duration of {ms} ms is too long")))?;
+            if let Some(remaining) = int.remaining_time() {
+                duration = duration.min(remaining);
+            }
+            int.clock().sleep(duration);
+            Ok(Value::Nil)
+        }),
+        // builds a time from a year, month, and day, and optionally an hour, minute, and second
+        NativeFunction::new("date", Arity::range(3, 6), |_, args| {
+            let mut parts = [0; 6];
+            for (part, arg) in parts.iter_mut().zip(&args) {
+                let n = arg.as_number()?;
+                if n.fract() != 0.0 || n.abs() > i32::MAX as f64 {
+                    let msg = format!("expected an integer but got {n}");
+                    return Err(ValueError::Custom(msg).into());
+                }
+                *part = n as i64;
+            }
+            let [year, month, day, hour, minute, second] = parts;
+            let dt = DateTime {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                millis: 0,
+            };
+            Ok((dt.to_ms()? as f64).into())
+        }),
+        NativeFunction::new("format_time", 2, |_, args| {
+            let ts = args[0].as_number()?;
+            let dt = DateTime::from_ms(ts.floor() as i64);
+            Ok(dt.format(args[1].as_str()?)?.into())
+        }),
+        NativeFunction::new("parse_time", 2, |_, args| {
+            let dt = DateTime::parse(args[0].as_str()?, args[1].as_str()?)?;
+            Ok((dt.to_ms()? as f64).into())
+        }),
+        duration("seconds", 1000.0),
+        duration("minutes", 60_000.0),
+        duration("hours", 3_600_000.0),
+        duration("days", MS_PER_DAY as f64),
+    ];
+    int.define_module("time", bindings.map(NativeFunction::binding));
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// a function that converts a number of some unit into a duration in ms
+fn duration(name: &str, unit_ms: f64) -> NativeFunction {
+    NativeFunction::new(name, 1, move |_, args| {
+        Ok((args[0].as_number()? * unit_ms).into())
+    })
+}
+
+/// A UTC date and time, broken down into its calendar fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    pub millis: i64,
+}
+
+impl DateTime {
+    pub fn from_ms(ts: i64) -> Self {
+        let days = ts.div_euclid(MS_PER_DAY);
+        let ms = ts.rem_euclid(MS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year,
+            month,
+            day,
+            hour: ms / 3_600_000,
+            minute: ms / 60_000 % 60,
+            second: ms / 1000 % 60,
+            millis: ms % 1000,
+        }
+    }
+
+    /// Converts the fields into a time in ms since the epoch, returning an error if any of them
+    /// are out of range.
+    pub fn to_ms(&self) -> Result<i64, ValueError> {
+        let in_range = (-MAX_YEAR..=MAX_YEAR).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day);
+        let days = if in_range {
+            days_from_civil(self.year, self.month, self.day)
+        } else {
+            0
+        };
+        let valid = in_range
+            && civil_from_days(days) == (self.year, self.month, self.day)
+            && (0..24).contains(&self.hour)
+            && (0..60).contains(&self.minute)
+            && (0..60).contains(&self.second)
+            && (0..1000).contains(&self.millis);
+        let ms = ((self.hour * 60 + self.minute) * 60 + self.second) * 1000 + self.millis;
+        let total = days
+            .checked_mul(MS_PER_DAY)
+            .and_then(|day_ms| day_ms.checked_add(ms));
+        match total {
+            Some(total) if valid => Ok(total),
+            _ => {
+                let Self {
+                    year,
+                    month,
+                    day,
+                    hour,
+                    minute,
+                    second,
+                    millis,
+                } = self;
+                Err(ValueError::Custom(format!(
+                    "invalid date {year}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}.{millis:03}"
+                )))
+            }
+        }
+    }
+
+    /// Formats the date with strftime style specifiers: `%Y` year, `%m` month, `%d` day, `%H`
+    /// hour, `%M` minute, `%S` second, `%L` millisecond, and `%%` for a literal `%`. Every field
+    /// but the year is zero padded.
+    pub fn format(&self, fmt: &str) -> Result<String, ValueError> {
+        let mut out = String::new();
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let _ = match chars.next() {
+                Some('Y') => write!(out, "{}", self.year),
+                Some('m') => write!(out, "{:02}", self.month),
+                Some('d') => write!(out, "{:02}", self.day),
+                Some('H') => write!(out, "{:02}", self.hour),
+                Some('M') => write!(out, "{:02}", self.minute),
+                Some('S') => write!(out, "{:02}", self.second),
+                Some('L') => write!(out, "{:03}", self.millis),
+                Some('%') => write!(out, "%"),
+                other => return Err(invalid_specifier(other)),
+            };
+        }
+        Ok(out)
+    }
+
+    /// Parses a date with the specifiers of [`DateTime::format`]. Fields that are missing from
+    /// the format default to the start of the epoch's year, month, or day.
+    pub fn parse(src: &str, fmt: &str) -> Result<Self, ValueError> {
+        let mismatch = || ValueError::Custom(format!("time '{src}' does not match format '{fmt}'"));
+        let mut dt = Self {
+            year: 1970,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            millis: 0,
+        };
+        let mut rest = src;
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                rest = rest.strip_prefix(c).ok_or_else(mismatch)?;
+                continue;
+            }
+            let (field, max_digits) = match chars.next() {
+                Some('Y') => (&mut dt.year, 4),
+                Some('m') => (&mut dt.month, 2),
+                Some('d') => (&mut dt.day, 2),
+                Some('H') => (&mut dt.hour, 2),
+                Some('M') => (&mut dt.minute, 2),
+                Some('S') => (&mut dt.second, 2),
+                Some('L') => (&mut dt.millis, 3),
+                Some('%') => {
+                    rest = rest.strip_prefix('%').ok_or_else(mismatch)?;
+                    continue;
+                }
+                other => return Err(invalid_specifier(other)),
+            };
+            let digits = rest
+                .bytes()
+                .take(max_digits)
+                .take_while(u8::is_ascii_digit)
+                .count();
+            *field = rest[..digits].parse().map_err(|_| mismatch())?;
+            rest = &rest[digits..];
+        }
+        if !rest.is_empty() {
+            return Err(mismatch());
+        }
+        dt.to_ms()?;
+        Ok(dt)
+    }
+}
+
+fn invalid_specifier(c: Option<char>) -> ValueError {
+    let spec = c.map(|c| format!("%{c}")).unwrap_or("%".into());
+    ValueError::Custom(format!("invalid time format specifier '{spec}'"))
+}
+
+// the number of days since the epoch of a date in the proleptic gregorian calendar, from
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// the inverse of days_from_civil
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
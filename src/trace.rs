@@ -0,0 +1,70 @@
+use crate::prelude::*;
+use std::fmt::Display;
+
+/// A single active call on the interpreter's call stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// the name of the function that was called
+    pub name: String,
+    /// the line of the call site in the caller, or None if called by the host
+    pub line: Option<usize>,
+    /// the path of the imported module that the call site is in, or None if it is in the script
+    pub module: Option<Rc<Path>>,
+}
+
+/// A snapshot of the call stack taken at the point where a runtime error was raised.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Traceback {
+    frames: Vec<Frame>,
+    // the line of the error in the innermost frame, if it is known.
+    line: Option<usize>,
+    // the path of the imported module that the error was raised in, or None for the script
+    module: Option<Rc<Path>>,
+}
+
+impl Traceback {
+    pub fn new(frames: Vec<Frame>, line: Option<usize>, module: Option<Rc<Path>>) -> Self {
+        Self {
+            frames,
+            line,
+            module,
+        }
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+}
+
+impl Display for Traceback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Traceback (most recent call last):")?;
+        // each frame's call site is the line currently executing in its caller, so the
+        // entries are printed against the name of the enclosing function.
+        let outermost = match self.frames.first() {
+            Some(Frame { line: None, .. }) => "<host>",
+            _ => "<script>",
+        };
+        let callers = std::iter::once(outermost).chain(self.frames.iter().map(|f| f.name.as_str()));
+        let lines = self
+            .frames
+            .iter()
+            .map(|f| (f.line, f.module.as_deref()))
+            .chain(std::iter::once((self.line, self.module.as_deref())));
+        // runs of identical entries, as left by deep recursion, are collapsed into one.
+        let entries = callers.zip(lines).dedup_with_count();
+        for (count, (name, (line, module))) in entries {
+            match (line, module) {
+                (Some(line), Some(module)) => {
+                    writeln!(f, "  {}, line {line}, in {name}", module.display())?
+                }
+                (Some(line), None) => writeln!(f, "  line {line}, in {name}")?,
+                (None, _) => writeln!(f, "  in {name}")?,
+            }
+            if count > 1 {
+                writeln!(f, "  ... previous frame repeated {} more times", count - 1)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -8,6 +8,10 @@ pub enum Value {
     Function(Function),
     Class(Class),
     Instance(Instance),
+    List(List),
+    Map(Map),
+    Native(NativeInstance),
+    Module(Module),
     Nil,
     Undefined,
 }
@@ -22,9 +26,101 @@ impl From<&str> for Value {
 pub enum ValueError {
     #[error("value was not a number")]
     NotANumber,
+
+    #[error("expected {expected} but got {actual}")]
+    Type {
+        expected: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = ValueError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_number()
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ValueError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(other.type_error("string")),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = ValueError;
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.as_bool()
+    }
 }
 
 impl Value {
+    /// The name of the type of this value as seen by lox scripts.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Bool(_) => "bool",
+            Self::Function(_) => "function",
+            Self::Class(_) => "class",
+            Self::Instance(_) | Self::Native(_) => "instance",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+            Self::Module(_) => "module",
+            Self::Nil => "nil",
+            Self::Undefined => "undefined",
+        }
+    }
+
+    pub fn as_number(&self) -> Result<f64, ValueError> {
+        match self {
+            Self::Number(n) => Ok(*n),
+            _ => Err(self.type_error("number")),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&str, ValueError> {
+        match self {
+            Self::String(s) => Ok(s),
+            _ => Err(self.type_error("string")),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, ValueError> {
+        match self {
+            Self::Bool(b) => Ok(*b),
+            _ => Err(self.type_error("bool")),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&List, ValueError> {
+        match self {
+            Self::List(l) => Ok(l),
+            _ => Err(self.type_error("list")),
+        }
+    }
+
+    pub fn as_map(&self) -> Result<&Map, ValueError> {
+        match self {
+            Self::Map(m) => Ok(m),
+            _ => Err(self.type_error("map")),
+        }
+    }
+
+    pub(crate) fn type_error(&self, expected: &'static str) -> ValueError {
+        ValueError::Type {
+            expected,
+            actual: self.type_name(),
+        }
+    }
+
     pub fn as_callable(&self) -> Option<&dyn Callable> {
         match self {
             Value::Function(f) => Some(f),
@@ -33,6 +129,8 @@ impl Value {
         }
     }
 
+    /// Converts the value into a string without running any lox code, so instances are shown
+    /// as their class name. [`Interpreter::stringify`] calls their `__str` methods instead.
     pub fn to_lox(&self) -> String {
         match self {
             Self::Number(v) => v.to_string(),
@@ -43,12 +141,20 @@ impl Value {
             Self::Function(f) => f.to_string(),
             Self::Class(c) => c.to_string(),
             Self::Instance(i) => i.to_string(),
+            Self::List(l) => l.to_string(),
+            Self::Map(m) => m.to_string(),
+            Self::Native(n) => n.to_string(),
+            Self::Module(m) => m.to_string(),
         }
     }
     pub fn truthy(&self) -> bool {
         match self {
             Self::Class(_)
             | Self::Instance(_)
+            | Self::List(_)
+            | Self::Map(_)
+            | Self::Native(_)
+            | Self::Module(_)
             | Self::Number(_)
             | Self::String(_)
             | Self::Function(_) => true,
@@ -69,6 +175,10 @@ impl std::fmt::Display for Value {
             Self::Function(func) => func.fmt(f),
             Self::Class(c) => c.fmt(f),
             Self::Instance(i) => i.fmt(f),
+            Self::List(l) => l.fmt(f),
+            Self::Map(m) => m.fmt(f),
+            Self::Native(n) => n.fmt(f),
+            Self::Module(m) => m.fmt(f),
         }
     }
 }